@@ -0,0 +1,43 @@
+//! Benchmarks for instruction decoding and execution.
+//!
+//! Usage: `cargo bench --features bench`
+
+use std::hint::black_box;
+
+use chip8::{Cpu, Instruction};
+use criterion::{criterion_group, criterion_main, Criterion};
+
+/// A tight loop of arithmetic, a skip, a memory access and a jump back to the start.
+const LOOP: [u8; 12] = [
+    0x70, 0x01,     // ADD V0, 1
+    0x81, 0x04,     // ADD V1, V0
+    0x30, 0x00,     // SE V0, 0
+    0xA3, 0x00,     // LD I, 0x300
+    0xF1, 0x1E,     // ADD I, V1
+    0x12, 0x00,     // JP 0x200
+];
+
+fn decode(c: &mut Criterion) {
+    c.bench_function("decode all opcodes", |b| {
+        b.iter(|| {
+            for opcode in 0..=u16::MAX {
+                let _ = black_box(Instruction::try_decode(black_box(opcode)));
+            }
+        })
+    });
+}
+
+fn cycle(c: &mut Criterion) {
+    let mut cpu = Cpu::new();
+    cpu.load_rom(&LOOP).unwrap();
+    c.bench_function("run 1M cycles", |b| {
+        b.iter(|| {
+            for _ in 0..1_000_000 {
+                cpu.cycle().unwrap();
+            }
+        })
+    });
+}
+
+criterion_group!(benches, decode, cycle);
+criterion_main!(benches);
@@ -0,0 +1,35 @@
+//! Run the classic IBM logo ROM and print the screen.
+//!
+//! Usage: `cargo run --example ibm_logo`
+
+use std::process;
+
+use chip8::Cpu;
+
+/// The IBM logo test ROM: clears the screen, draws the logo as six 8x15 sprites, then jumps to
+/// itself.
+const IBM_LOGO: [u8; 132] = [
+    0x00, 0xE0, 0xA2, 0x2A, 0x60, 0x0C, 0x61, 0x08, 0xD0, 0x1F, 0x70, 0x09, 0xA2, 0x39, 0xD0, 0x1F,
+    0xA2, 0x48, 0x70, 0x08, 0xD0, 0x1F, 0x70, 0x04, 0xA2, 0x57, 0xD0, 0x1F, 0x70, 0x08, 0xA2, 0x66,
+    0xD0, 0x1F, 0x70, 0x08, 0xA2, 0x75, 0xD0, 0x1F, 0x12, 0x28, 0xFF, 0x00, 0xFF, 0x00, 0x3C, 0x00,
+    0x3C, 0x00, 0x3C, 0x00, 0x3C, 0x00, 0xFF, 0x00, 0xFF, 0xFF, 0x00, 0xFF, 0x00, 0x38, 0x00, 0x3F,
+    0x00, 0x3F, 0x00, 0x38, 0x00, 0xFF, 0x00, 0xFF, 0x80, 0x00, 0xE0, 0x00, 0xE0, 0x00, 0x80, 0x00,
+    0x80, 0x00, 0xE0, 0x00, 0xE0, 0x00, 0x80, 0xF8, 0x00, 0xFC, 0x00, 0x3E, 0x00, 0x3F, 0x00, 0x3B,
+    0x00, 0x39, 0x00, 0xF8, 0x00, 0xF8, 0x03, 0x00, 0x07, 0x00, 0x0F, 0x00, 0xBF, 0x00, 0xFB, 0x00,
+    0xF3, 0x00, 0xE3, 0x00, 0x43, 0xE0, 0x00, 0xE0, 0x00, 0x80, 0x00, 0x80, 0x00, 0x80, 0x00, 0x80,
+    0x00, 0xE0, 0x00, 0xE0,
+];
+
+/// More than enough cycles for the ROM to finish drawing.
+const MAX_CYCLES: usize = 1000;
+
+fn main() {
+    let mut cpu = Cpu::new();
+    cpu.load_rom(&IBM_LOGO).expect("the ROM fits in memory");
+
+    if let Err(e) = cpu.run_until_halt(MAX_CYCLES) {
+        eprintln!("{e}");
+        process::exit(1);
+    }
+    print!("{}", cpu.render_ascii());
+}
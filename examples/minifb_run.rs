@@ -0,0 +1,78 @@
+//! Play a ROM in a window.
+//!
+//! Usage: `cargo run --features minifb --example minifb_run -- path/to/rom.ch8`
+//!
+//! The keypad is mapped to the left side of a QWERTY keyboard:
+//! ```text
+//! 1 2 3 C        1 2 3 4
+//! 4 5 6 D   <-   Q W E R
+//! 7 8 9 E        A S D F
+//! A 0 B F        Z X C V
+//! ```
+
+use std::process;
+
+use chip8::{Cpu, DISPLAY_HEIGHT, DISPLAY_WIDTH};
+use minifb::{Key, Scale, Window, WindowOptions};
+
+const CPU_HZ: usize = 700;
+const FRAME_HZ: usize = 60;
+
+const ON_COLOR: u32 = 0x00ff_ffff;
+const OFF_COLOR: u32 = 0x0000_0000;
+
+/// Keyboard key for each Chip-8 key, indexed by Chip-8 key.
+const KEYMAP: [Key; 16] = [
+    Key::X,    Key::Key1, Key::Key2, Key::Key3,     // 0 1 2 3
+    Key::Q,    Key::W,    Key::E,    Key::A,        // 4 5 6 7
+    Key::S,    Key::D,    Key::Z,    Key::C,        // 8 9 A B
+    Key::Key4, Key::R,    Key::F,    Key::V,        // C D E F
+];
+
+fn main() {
+    let Some(path) = std::env::args().nth(1) else {
+        eprintln!("usage: minifb_run <rom>");
+        process::exit(1);
+    };
+    let rom = std::fs::read(&path).unwrap_or_else(|e| {
+        eprintln!("failed to read {path}: {e}");
+        process::exit(1);
+    });
+
+    let mut cpu = Cpu::new();
+    if let Err(e) = cpu.load_rom(&rom) {
+        eprintln!("failed to load {path}: {e}");
+        process::exit(1);
+    }
+
+    let options = WindowOptions {
+        scale: Scale::X16,
+        ..WindowOptions::default()
+    };
+    let mut window = Window::new("Chip-8", DISPLAY_WIDTH, DISPLAY_HEIGHT, options).unwrap_or_else(|e| {
+        eprintln!("failed to open window: {e}");
+        process::exit(1);
+    });
+    window.set_target_fps(FRAME_HZ);
+
+    let mut buffer = Vec::new();
+    while window.is_open() && !window.is_key_down(Key::Escape) {
+        cpu.set_keys(KEYMAP.map(|key| window.is_key_down(key)));
+
+        if let Err(e) = cpu.run_frame(CPU_HZ / FRAME_HZ) {
+            eprintln!("{e}");
+            process::exit(1);
+        }
+
+        // The window stretches the buffer, so high resolution mode needs no special handling
+        let (width, height) = cpu.display_size();
+        if cpu.take_dirty() || buffer.len() != width * height {
+            buffer.clear();
+            buffer.extend(cpu.framebuffer().iter().map(|&pixel| if pixel { ON_COLOR } else { OFF_COLOR }));
+        }
+        if let Err(e) = window.update_with_buffer(&buffer, width, height) {
+            eprintln!("failed to update window: {e}");
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,319 @@
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::error::AsmError;
+use crate::instruction::Instruction;
+
+const PROGRAM_START_ADDRESS: u16 = 0x200;
+
+/// An operand of an instruction, before numbers and labels are resolved.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Operand<'a> {
+    Reg(u8),
+    I,
+    IndirectI,
+    Dt,
+    St,
+    K,
+    F,
+    B,
+    Hf,
+    R,
+    /// `LONG addr`, the 16-bit operand of `LD I, LONG addr`
+    Long(&'a str),
+    /// A number or label
+    Value(&'a str),
+}
+
+fn parse_operand(text: &str) -> Operand<'_> {
+    let upper = text.to_ascii_uppercase();
+    match upper.as_str() {
+        "I" => return Operand::I,
+        "[I]" => return Operand::IndirectI,
+        "DT" => return Operand::Dt,
+        "ST" => return Operand::St,
+        "K" => return Operand::K,
+        "F" => return Operand::F,
+        "B" => return Operand::B,
+        "HF" => return Operand::Hf,
+        "R" => return Operand::R,
+        _ => {},
+    }
+
+    if upper.len() == 2 && upper.starts_with('V') {
+        if let Some(reg) = upper[1..].chars().next().and_then(|c| c.to_digit(16)) {
+            return Operand::Reg(reg as u8);
+        }
+    }
+    if upper.starts_with("LONG ") {
+        return Operand::Long(text[5..].trim());
+    }
+    Operand::Value(text)
+}
+
+/// Parse a decimal, `0x` hexadecimal or `0b` binary number.
+pub(crate) fn parse_number(text: &str) -> Option<u32> {
+    if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        u32::from_str_radix(hex, 16).ok()
+    } else if let Some(bin) = text.strip_prefix("0b").or_else(|| text.strip_prefix("0B")) {
+        u32::from_str_radix(bin, 2).ok()
+    } else if text.starts_with(|c: char| c.is_ascii_digit()) {
+        text.parse().ok()
+    } else {
+        None
+    }
+}
+
+fn is_label(text: &str) -> bool {
+    text.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && text.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Resolves operand values for one line.
+struct Resolver<'a> {
+    line: usize,
+    labels: &'a dyn Fn(&str) -> Option<u16>,
+}
+
+impl Resolver<'_> {
+    /// Resolve a number of at most `max`.
+    fn number(&self, text: &str, max: u32) -> Result<u32, AsmError> {
+        let value = parse_number(text).ok_or(AsmError::Syntax { line: self.line })?;
+        if value > max {
+            return Err(AsmError::OutOfRange { line: self.line });
+        }
+        Ok(value)
+    }
+
+    /// Resolve a number or label of at most `max`.
+    fn address(&self, text: &str, max: u32) -> Result<u16, AsmError> {
+        if !is_label(text) {
+            return self.number(text, max).map(|value| value as u16);
+        }
+        let Some(addr) = (self.labels)(text) else {
+            return Err(AsmError::UnknownLabel { line: self.line, label: text.to_string() });
+        };
+        if addr as u32 > max {
+            return Err(AsmError::OutOfRange { line: self.line });
+        }
+        Ok(addr)
+    }
+
+    fn addr(&self, text: &str) -> Result<u16, AsmError> {
+        self.address(text, 0xfff)
+    }
+
+    fn byte(&self, text: &str) -> Result<u8, AsmError> {
+        self.number(text, 0xff).map(|value| value as u8)
+    }
+
+    fn nibble(&self, text: &str) -> Result<u8, AsmError> {
+        self.number(text, 0xf).map(|value| value as u8)
+    }
+}
+
+/// Parse one instruction, looking up labels with `labels`. `line` is only used for errors.
+pub(crate) fn parse_instruction(
+    text: &str,
+    line: usize,
+    labels: &dyn Fn(&str) -> Option<u16>,
+) -> Result<Instruction, AsmError> {
+    use Instruction::*;
+    use Operand::*;
+
+    let text = text.trim();
+    let (mnemonic, rest) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+    let operands: Vec<Operand> = if rest.trim().is_empty() {
+        Vec::new()
+    } else {
+        rest.split(',').map(|operand| parse_operand(operand.trim())).collect()
+    };
+    let r = Resolver { line, labels };
+
+    let instr = match (mnemonic.to_ascii_uppercase().as_str(), operands.as_slice()) {
+        ("CLS",  []) => Cls,
+        ("RET",  []) => Ret,
+        ("SYS",  [Value(a)]) => Sys(r.addr(a)?),
+        ("JP",   [Value(a)]) => JpImm(r.addr(a)?),
+        ("JP",   [Reg(0), Value(a)]) => JpReg(r.addr(a)?),
+        ("CALL", [Value(a)]) => Call(r.addr(a)?),
+        ("SE",   [Reg(x), Reg(y)]) => SeReg(*x, *y),
+        ("SE",   [Reg(x), Value(k)]) => SeImm(*x, r.byte(k)?),
+        ("SNE",  [Reg(x), Reg(y)]) => SneReg(*x, *y),
+        ("SNE",  [Reg(x), Value(k)]) => SneImm(*x, r.byte(k)?),
+        ("LD",   [Reg(x), Reg(y)]) => LdReg(*x, *y),
+        ("LD",   [Reg(x), Value(k)]) => LdImm(*x, r.byte(k)?),
+        ("LD",   [I, Value(a)]) => LdI(r.addr(a)?),
+        ("LD",   [I, Long(a)]) => LdILong(r.address(a, 0xffff)?),
+        ("LD",   [Reg(x), Dt]) => LdRegDt(*x),
+        ("LD",   [Reg(x), K]) => LdRegK(*x),
+        ("LD",   [Dt, Reg(x)]) => LdDtReg(*x),
+        ("LD",   [St, Reg(x)]) => LdStReg(*x),
+        ("LD",   [F, Reg(x)]) => LdF(*x),
+        ("LD",   [Hf, Reg(x)]) => LdHf(*x),
+        ("LD",   [B, Reg(x)]) => LdB(*x),
+        ("LD",   [IndirectI, Reg(x)]) => LdMemReg(*x),
+        ("LD",   [Reg(x), IndirectI]) => LdRegMem(*x),
+        ("LD",   [R, Reg(x)]) => LdRplReg(*x),
+        ("LD",   [Reg(x), R]) => LdRegRpl(*x),
+        ("ADD",  [Reg(x), Reg(y)]) => AddReg(*x, *y),
+        ("ADD",  [Reg(x), Value(k)]) => AddImm(*x, r.byte(k)?),
+        ("ADD",  [I, Reg(x)]) => AddI(*x),
+        ("OR",   [Reg(x), Reg(y)]) => OrReg(*x, *y),
+        ("AND",  [Reg(x), Reg(y)]) => AndReg(*x, *y),
+        ("XOR",  [Reg(x), Reg(y)]) => XorReg(*x, *y),
+        ("SUB",  [Reg(x), Reg(y)]) => SubReg(*x, *y),
+        ("SUBN", [Reg(x), Reg(y)]) => Subn(*x, *y),
+        ("SHR",  [Reg(x), Reg(y)]) => Shr(*x, *y),
+        ("SHR",  [Reg(x)]) => Shr(*x, *x),
+        ("SHL",  [Reg(x), Reg(y)]) => Shl(*x, *y),
+        ("SHL",  [Reg(x)]) => Shl(*x, *x),
+        ("RND",  [Reg(x), Value(k)]) => Rnd(*x, r.byte(k)?),
+        ("DRW",  [Reg(x), Reg(y), Value(n)]) => Drw(*x, *y, r.nibble(n)?),
+        ("SKP",  [Reg(x)]) => Skp(*x),
+        ("SKNP", [Reg(x)]) => Sknp(*x),
+        ("SCD",  [Value(n)]) => ScrollDown(r.nibble(n)?),
+        ("SCR",  []) => ScrollRight,
+        ("SCL",  []) => ScrollLeft,
+        ("LOW",  []) => Low,
+        ("HIGH", []) => High,
+        ("PLANE", [Value(n)]) => Plane(r.number(n, 3)? as u8),
+        ("AUDIO", []) => Audio,
+        ("PITCH", [Reg(x)]) => Pitch(*x),
+        _ => return Err(AsmError::Syntax { line }),
+    };
+    Ok(instr)
+}
+
+/// A line of source with its label and comment removed.
+struct Statement<'a> {
+    line: usize,
+    text: &'a str,
+}
+
+impl Statement<'_> {
+    /// The operands of a `db` directive, if this is one.
+    fn db_operands(&self) -> Option<&str> {
+        let (directive, rest) = self.text.split_once(char::is_whitespace)?;
+        directive.eq_ignore_ascii_case("db").then_some(rest)
+    }
+
+    /// Assemble the statement, looking up labels with `labels`.
+    fn assemble(&self, labels: &dyn Fn(&str) -> Option<u16>) -> Result<Vec<u8>, AsmError> {
+        if let Some(rest) = self.db_operands() {
+            let r = Resolver { line: self.line, labels };
+            return rest.split(',').map(|byte| r.byte(byte.trim())).collect();
+        }
+
+        let instr = parse_instruction(self.text, self.line, labels)?;
+        let mut bytes = instr.encode().to_be_bytes().to_vec();
+        if let Instruction::LdILong(addr) = instr {
+            bytes.extend_from_slice(&addr.to_be_bytes());
+        }
+        Ok(bytes)
+    }
+}
+
+/// Assemble a program to be loaded at 0x200.
+///
+/// Each line holds an optional label definition (`start:`), then an optional instruction in
+/// the syntax of [`Instruction::from_asm`] or a `db` directive listing raw bytes
+/// (`db 0xF0, 0x90`). Everything after a `;` is a comment. Labels can be used wherever an
+/// address is expected, before or after their definition.
+pub fn assemble(source: &str) -> Result<Vec<u8>, AsmError> {
+    // First pass: find the address of every label
+    let mut labels = BTreeMap::new();
+    let mut statements = Vec::new();
+    let mut addr = PROGRAM_START_ADDRESS as usize;
+    for (i, text) in source.lines().enumerate() {
+        let line = i + 1;
+        let mut text = text.split(';').next().unwrap_or_default().trim();
+
+        if let Some((label, rest)) = text.split_once(':') {
+            let label = label.trim();
+            if !is_label(label) {
+                return Err(AsmError::Syntax { line });
+            }
+            if labels.insert(String::from(label), addr as u16).is_some() {
+                return Err(AsmError::DuplicateLabel { line, label: label.to_string() });
+            }
+            text = rest.trim();
+        }
+        if text.is_empty() {
+            continue;
+        }
+
+        // Labels may not be defined yet, so stand in any address to size the statement
+        let statement = Statement { line, text };
+        addr += statement.assemble(&|_| Some(0))?.len();
+        statements.push(statement);
+    }
+
+    // Second pass: assemble with every label known
+    let mut rom = Vec::new();
+    for statement in &statements {
+        rom.extend(statement.assemble(&|label| labels.get(label).copied())?);
+    }
+    Ok(rom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disassembler::disassemble;
+
+    #[test]
+    fn assemble_test() {
+        let source = "
+            ; Count V0 up to 10 forever
+            start:  LD V0, 0
+            loop:
+                    ADD V0, 1
+                    SE V0, 10
+                    JP loop
+                    LD I, sprite
+                    JP start
+            sprite: db 0xF0, 0x90, 0b11110000
+        ";
+        let rom = assemble(source).unwrap();
+        assert_eq!(rom, [
+            0x60, 0x00, 0x70, 0x01, 0x30, 0x0A, 0x12, 0x02, 0xA2, 0x0C, 0x12, 0x00,
+            0xF0, 0x90, 0xF0,
+        ]);
+
+        let listing: Vec<String> = disassemble(&rom[..12], 0x200)
+            .map(|(_, _, instr)| instr.unwrap().to_string())
+            .collect();
+        assert_eq!(listing, [
+            "LD V0, 0x00",
+            "ADD V0, 0x01",
+            "SE V0, 0x0A",
+            "JP 0x202",
+            "LD I, 0x20C",
+            "JP 0x200",
+        ]);
+    }
+
+    #[test]
+    fn assemble_long_test() {
+        let rom = assemble("LD I, LONG data\nJP 0x200\ndata: db 1").unwrap();
+        assert_eq!(rom, [0xF0, 0x00, 0x02, 0x06, 0x12, 0x00, 0x01]);
+    }
+
+    #[test]
+    fn assemble_error_test() {
+        assert_eq!(assemble("CLS\nJP nowhere"), Err(AsmError::UnknownLabel {
+            line: 2,
+            label: String::from("nowhere"),
+        }));
+        assert_eq!(assemble("a: CLS\na: CLS"), Err(AsmError::DuplicateLabel {
+            line: 2,
+            label: String::from("a"),
+        }));
+        assert_eq!(assemble("LD V0, 256"), Err(AsmError::OutOfRange { line: 1 }));
+        assert_eq!(assemble("PLANE 4"), Err(AsmError::OutOfRange { line: 1 }));
+        assert_eq!(assemble("CLS\n\nLD V0"), Err(AsmError::Syntax { line: 3 }));
+        assert_eq!(assemble("db 1, x"), Err(AsmError::Syntax { line: 1 }));
+    }
+}
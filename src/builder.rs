@@ -0,0 +1,178 @@
+use crate::cpu::{Cpu, FONT_BASE_ADDRESS, PROGRAM_START_ADDRESS};
+use crate::error::LoadError;
+use crate::quirks::Quirks;
+
+/// Builder for configuring a [`Cpu`] before it starts running.
+pub struct CpuBuilder<'a> {
+    seed: Option<u64>,
+    quirks: Quirks,
+    rom: Option<&'a [u8]>,
+    font_base: u16,
+    start_address: u16,
+}
+
+impl Default for CpuBuilder<'_> {
+    fn default() -> Self {
+        Self {
+            seed: None,
+            quirks: Quirks::default(),
+            rom: None,
+            font_base: FONT_BASE_ADDRESS,
+            start_address: PROGRAM_START_ADDRESS,
+        }
+    }
+}
+
+impl<'a> CpuBuilder<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Seed the random number generator used by `RND`.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Set the interpreter quirks.
+    pub fn quirks(mut self, quirks: Quirks) -> Self {
+        self.quirks = quirks;
+        self
+    }
+
+    /// Set a ROM to load at the program start address.
+    pub fn rom(mut self, rom: &'a [u8]) -> Self {
+        self.rom = Some(rom);
+        self
+    }
+
+    /// Set the address the font is stored at (0x100 by default). Some programs expect the
+    /// common 0x050. The font and big font take up 180 bytes, which must fit before the program
+    /// start address.
+    pub fn font_base(mut self, font_base: u16) -> Self {
+        self.font_base = font_base;
+        self
+    }
+
+    /// Set the address the ROM is loaded at and execution starts from (0x200 by default). The
+    /// ETI-660 used 0x600.
+    pub fn start_address(mut self, start_address: u16) -> Self {
+        self.start_address = start_address;
+        self
+    }
+
+    pub fn build(self) -> Result<Cpu, LoadError> {
+        let mut cpu = match self.seed {
+            Some(seed) => Cpu::with_seed(seed),
+            None => Cpu::new(),
+        };
+        cpu.set_quirks(self.quirks);
+        if self.start_address != PROGRAM_START_ADDRESS {
+            cpu.set_start_address(self.start_address)?;
+        }
+        // Check the font against the start address even when it stays at its default location
+        cpu.set_font_base(self.font_base)?;
+        if let Some(rom) = self.rom {
+            cpu.load_rom(rom)?;
+        }
+        Ok(cpu)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_test() {
+        let quirks = Quirks { jump_uses_vx: true, ..Quirks::default() };
+        let cpu = CpuBuilder::new()
+            .seed(1234)
+            .quirks(quirks)
+            .rom(&[0x60, 0x05, 0x12, 0x00])
+            .build()
+            .unwrap();
+
+        assert_eq!(cpu.quirks(), quirks);
+        assert_eq!(cpu.snapshot().memory[0x200..0x204], [0x60, 0x05, 0x12, 0x00]);
+    }
+
+    /// Build a CPU with the given font base and return the index LdF points at for digit 1.
+    fn font_address(font_base: u16) -> (Cpu, u16) {
+        let mut cpu = CpuBuilder::new()
+            .font_base(font_base)
+            .rom(&[0x60, 0x01, 0xF0, 0x29])
+            .build()
+            .unwrap();
+        cpu.cycle().unwrap();
+        cpu.cycle().unwrap();
+        let index = cpu.snapshot().index;
+        (cpu, index)
+    }
+
+    #[test]
+    fn default_font_base_test() {
+        let (cpu, index) = font_address(0x100);
+        assert_eq!(index, 0x105);
+        assert_eq!(cpu.memory_slice(index, 5), [0x20, 0x60, 0x20, 0x20, 0x70]);
+    }
+
+    #[test]
+    fn font_base_050_test() {
+        let (cpu, index) = font_address(0x050);
+        assert_eq!(index, 0x055);
+        assert_eq!(cpu.memory_slice(index, 5), [0x20, 0x60, 0x20, 0x20, 0x70]);
+        // The default location is cleared
+        assert!(cpu.memory_slice(0x104, 0x200 - 0x104).iter().all(|&byte| byte == 0));
+    }
+
+    #[test]
+    fn font_overlaps_program_test() {
+        let result = CpuBuilder::new().font_base(0x1a0).build();
+        assert_eq!(result.err(), Some(LoadError::FontOverlapsProgram { font_base: 0x1a0 }));
+        assert!(CpuBuilder::new().font_base(0x200 - 180).build().is_ok());
+    }
+
+    #[test]
+    fn start_address_test() {
+        let mut cpu = CpuBuilder::new()
+            .start_address(0x600)
+            .rom(&[0x60, 0x05, 0x16, 0x00])
+            .build()
+            .unwrap();
+        assert_eq!(cpu.snapshot().pc, 0x600);
+        assert_eq!(cpu.memory_slice(0x600, 4), [0x60, 0x05, 0x16, 0x00]);
+        assert!(cpu.memory_slice(0x200, 4).iter().all(|&byte| byte == 0));
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.registers()[0], 0x05);
+        assert_eq!(cpu.snapshot().pc, 0x602);
+
+        // Resetting starts over from the same address
+        cpu.reset();
+        assert_eq!(cpu.snapshot().pc, 0x600);
+
+        let result = CpuBuilder::new().start_address(0x1000).build();
+        assert_eq!(result.err(), Some(LoadError::StartOutOfMemory { start: 0x1000 }));
+    }
+
+    #[test]
+    fn font_base_start_address_test() {
+        // The font only has to fit before the program start address
+        let cpu = CpuBuilder::new().start_address(0x600).font_base(0x400).build().unwrap();
+        assert_eq!(cpu.memory_slice(0x405, 5), [0x20, 0x60, 0x20, 0x20, 0x70]);
+
+        let result = CpuBuilder::new().start_address(0x300).font_base(0x280).build();
+        assert_eq!(result.err(), Some(LoadError::FontOverlapsProgram { font_base: 0x280 }));
+
+        // The default font at 0x100 overlaps a program starting at 0x120
+        let result = CpuBuilder::new().start_address(0x120).build();
+        assert_eq!(result.err(), Some(LoadError::FontOverlapsProgram { font_base: 0x100 }));
+    }
+
+    #[test]
+    fn build_rom_too_large_test() {
+        let rom = [0; 4096];
+        let result = CpuBuilder::new().rom(&rom).build();
+        assert_eq!(result.err(), Some(LoadError::RomTooLarge { size: 4096, max: 0xe00 }));
+    }
+}
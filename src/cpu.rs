@@ -1,17 +1,85 @@
 
 #![allow(dead_code)]
 
+use alloc::boxed::Box;
+use alloc::collections::VecDeque;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::display::{Display, PLANE_COUNT};
+use crate::error::{DecodeError, ExecError, LoadError};
 use crate::instruction::Instruction;
+use crate::quirks::Quirks;
+use crate::rng::{Rng, XorShift};
 
-const FONT_BASE_ADDRESS: u16 = 0x100;
+pub(crate) const PROGRAM_START_ADDRESS: u16 = 0x200; // Most Chip-8 programs start at this address
+pub(crate) const FONT_BASE_ADDRESS: u16 = 0x100;     // Default location of the font
 const FONT_CHAR_SIZE: u16 = 5;          // Font sprites are 5 bytes long (8x5 pixels)
+pub(crate) const TIMER_HZ: u128 = 60;   // The delay and sound timers count down at 60 Hz
+const DEFAULT_CLOCK_HZ: u32 = 700;      // A typical speed that suits most Chip-8 programs
+pub(crate) const NANOS_PER_SEC: u128 = 1_000_000_000;
+const BEEP_HZ: u32 = 440;               // Pitch of the buzzer's square wave
+const BEEP_AMPLITUDE: f32 = 0.25;       // Volume of the buzzer, between 0 and 1
+const DEFAULT_AUDIO_PITCH: u8 = 64;     // XO-CHIP pitch for a 4000 Hz pattern playback rate
+const MAX_REWIND_STATES: usize = 1024;  // Each rewind state holds a copy of memory
+const MAX_DIAGNOSTICS: usize = 1024;    // Later diagnostics are dropped until taken
+
+/// Sprites for the hexadecimal digits 0-F.
+const FONT: [u8; 80] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0,   // 0
+    0x20, 0x60, 0x20, 0x20, 0x70,   // 1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0,   // 2
+    0xF0, 0x10, 0xF0, 0x10, 0xF0,   // 3
+    0x90, 0x90, 0xF0, 0x10, 0x10,   // 4
+    0xF0, 0x80, 0xF0, 0x10, 0xF0,   // 5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0,   // 6
+    0xF0, 0x10, 0x20, 0x40, 0x40,   // 7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0,   // 8
+    0xF0, 0x90, 0xF0, 0x10, 0xF0,   // 9
+    0xF0, 0x90, 0xF0, 0x90, 0x90,   // A
+    0xE0, 0x90, 0xE0, 0x90, 0xE0,   // B
+    0xF0, 0x80, 0x80, 0x80, 0xF0,   // C
+    0xE0, 0x90, 0x90, 0x90, 0xE0,   // D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0,   // E
+    0xF0, 0x80, 0xF0, 0x80, 0x80,   // F
+];
+
+const BIG_FONT_CHAR_SIZE: u16 = 10;     // Big font sprites are 10 bytes long (8x10 pixels)
+
+/// SUPER-CHIP sprites for the decimal digits 0-9.
+const BIG_FONT: [u8; 100] = [
+    0x3C, 0x7E, 0xE7, 0xC3, 0xC3, 0xC3, 0xC3, 0xE7, 0x7E, 0x3C,     // 0
+    0x18, 0x38, 0x58, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3C,     // 1
+    0x3E, 0x7F, 0xC3, 0x06, 0x0C, 0x18, 0x30, 0x60, 0xFF, 0xFF,     // 2
+    0x3C, 0x7E, 0xC3, 0x03, 0x0E, 0x0E, 0x03, 0xC3, 0x7E, 0x3C,     // 3
+    0x06, 0x0E, 0x1E, 0x36, 0x66, 0xC6, 0xFF, 0xFF, 0x06, 0x06,     // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFE, 0x03, 0xC3, 0x7E, 0x3C,     // 5
+    0x3E, 0x7C, 0xC0, 0xC0, 0xFC, 0xFE, 0xC3, 0xC3, 0x7E, 0x3C,     // 6
+    0xFF, 0xFF, 0x03, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x60, 0x60,     // 7
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7E, 0x7E, 0xC3, 0xC3, 0x7E, 0x3C,     // 8
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7F, 0x3F, 0x03, 0x03, 0x3E, 0x7C,     // 9
+];
 
 /// Stack of 16 16-bit values used for storing memory addresses.
+#[derive(Clone, Debug)]
 pub struct Stack {
     bytes: [u16; 16],
     sp: usize,  // This is a usize so that it can be used to index a slice without type casting.
 }
 
+/// Stacks are equal if they hold the same values. Stale values above the stack pointer are
+/// ignored.
+impl PartialEq for Stack {
+    fn eq(&self, other: &Self) -> bool {
+        self.sp == other.sp && self.bytes[..self.sp] == other.bytes[..other.sp]
+    }
+}
+
+impl Eq for Stack {}
+
 impl Stack {
     /// Create a new stack with all values initialized to zero.
     fn new() -> Self {
@@ -21,219 +89,3593 @@ impl Stack {
         }
     }
 
-    /// Push a value onto the stack and wrap the stack pointer if necessary.
-    fn push(&mut self, data: u16) {
+    /// Push a value onto the stack, or return false if the stack is full.
+    fn push(&mut self, data: u16) -> bool {
+        if self.sp == self.bytes.len() {
+            return false;
+        }
+
         self.bytes[self.sp] = data;
         self.sp += 1;
+        true
+    }
 
-        // Wrap around stack pointer if it goes past the end of the stack
-        if self.sp > (15) {
-            self.sp = 0
+    /// Pop a value from the stack, or return `None` if the stack is empty.
+    fn pop(&mut self) -> Option<u16> {
+        if self.sp == 0 {
+            return None;
         }
+
+        self.sp -= 1;
+        Some(self.bytes[self.sp])
     }
 
-    /// Pop a value from the stack and wrap the stack pointer if necessary.
-    fn pop(&mut self) -> u16 {
-        if self.sp == 0 {
-            self.sp = 15;
-        } else {
-            self.sp -= 1;
+}
+
+/// Callback invoked before each instruction is executed with the address of the instruction,
+/// the raw opcode, and the decoded instruction.
+pub type TraceHook = Box<dyn FnMut(u16, u16, &Instruction)>;
+
+/// What [`Cpu::cycle`] should do, as decided by a [`PreCycleHook`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Control {
+    /// Execute the instruction as usual.
+    Continue,
+    /// Return without doing anything, leaving PC unchanged.
+    Pause,
+    /// Return [`ExecError::Aborted`] without doing anything.
+    Abort,
+}
+
+/// Callback invoked at the start of each cycle with PC, deciding whether the cycle goes ahead.
+pub type PreCycleHook = Box<dyn FnMut(u16) -> Control>;
+
+/// A change in the state of a key (0x0-0xF), queued with [`Cpu::push_key_event`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyEvent {
+    Pressed(u8),
+    Released(u8),
+}
+
+/// A suspicious but legal event noticed while executing, collected if diagnostics are enabled
+/// with [`Cpu::set_diagnostics_enabled`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Diagnostic {
+    /// The JP, CALL or JP V0 at `pc` jumped to an odd address, which usually means the program
+    /// is about to execute data or the second half of an instruction.
+    UnalignedJump { pc: u16, target: u16 },
+}
+
+/// What happened during a call to [`Cpu::run_frame`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FrameEvents {
+    /// A sprite drawn during the frame turned off a lit pixel
+    pub collision_occurred: bool,
+    /// A sprite was drawn during the frame
+    pub draw_occurred: bool,
+    /// The sound timer went from zero to nonzero, so the buzzer started sounding
+    pub beep_started: bool,
+}
+
+/// Relative time taken to execute `instr`, in machine cycles. Simple register and control flow
+/// instructions cost 1; instructions that touch many bytes of memory or the display cost more,
+/// roughly in proportion to how much slower they were on the original interpreters.
+fn cost(instr: &Instruction) -> u32 {
+    use Instruction::*;
+    match instr {
+        Cls => 4,
+        Drw(..) => 8,
+        ScrollDown(..) | ScrollRight | ScrollLeft => 4,
+        LdB(..) | Rnd(..) => 2,
+        LdMemReg(..) | LdRegMem(..) | LdRplReg(..) | LdRegRpl(..) | Audio => 2,
+        _ => 1,
+    }
+}
+
+/// Snapshot of the complete machine state, used for save states and debugging.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CpuState<const MEM: usize = 4096> {
+    pub pc: u16,
+    pub index: u16,
+    pub reg: [u8; 16],
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    #[cfg_attr(feature = "serde", serde(with = "memory_serde"))]
+    pub memory: [u8; MEM],
+    pub stack: [u16; 16],
+    pub sp: usize,
+    pub rpl: [u8; 8],
+}
+
+/// One difference between two [`CpuState`]s, found by [`CpuState::diff`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StateChange {
+    Pc { old: u16, new: u16 },
+    Index { old: u16, new: u16 },
+    /// Register V`reg` changed
+    Register { reg: u8, old: u8, new: u8 },
+    DelayTimer { old: u8, new: u8 },
+    SoundTimer { old: u8, new: u8 },
+    Memory { addr: u16, old: u8, new: u8 },
+    StackPointer { old: usize, new: usize },
+    /// The stack entry at `slot` changed. Only entries below either stack pointer are compared.
+    StackEntry { slot: usize, old: u16, new: u16 },
+    /// RPL flag `flag` changed
+    Rpl { flag: u8, old: u8, new: u8 },
+}
+
+impl<const MEM: usize> CpuState<MEM> {
+    /// List everything that differs between this state and `other`, with this state's values
+    /// as the old values. Snapshotting before and after a step shows what the step changed.
+    pub fn diff(&self, other: &CpuState<MEM>) -> Vec<StateChange> {
+        let mut changes = Vec::new();
+        if self.pc != other.pc {
+            changes.push(StateChange::Pc { old: self.pc, new: other.pc });
+        }
+        if self.index != other.index {
+            changes.push(StateChange::Index { old: self.index, new: other.index });
+        }
+        for (reg, (&old, &new)) in self.reg.iter().zip(&other.reg).enumerate() {
+            if old != new {
+                changes.push(StateChange::Register { reg: reg as u8, old, new });
+            }
+        }
+        if self.delay_timer != other.delay_timer {
+            changes.push(StateChange::DelayTimer { old: self.delay_timer, new: other.delay_timer });
+        }
+        if self.sound_timer != other.sound_timer {
+            changes.push(StateChange::SoundTimer { old: self.sound_timer, new: other.sound_timer });
+        }
+        for (addr, (&old, &new)) in self.memory.iter().zip(&other.memory).enumerate() {
+            if old != new {
+                changes.push(StateChange::Memory { addr: addr as u16, old, new });
+            }
+        }
+        if self.sp != other.sp {
+            changes.push(StateChange::StackPointer { old: self.sp, new: other.sp });
         }
+        let live = self.sp.max(other.sp).min(self.stack.len());
+        for slot in 0..live {
+            let (old, new) = (self.stack[slot], other.stack[slot]);
+            if old != new {
+                changes.push(StateChange::StackEntry { slot, old, new });
+            }
+        }
+        for (flag, (&old, &new)) in self.rpl.iter().zip(&other.rpl).enumerate() {
+            if old != new {
+                changes.push(StateChange::Rpl { flag: flag as u8, old, new });
+            }
+        }
+        changes
+    }
+}
 
-        self.bytes[self.sp]
+/// Serde helper for the memory array, since serde only implements its traits for arrays of up
+/// to 32 elements.
+#[cfg(feature = "serde")]
+mod memory_serde {
+    use alloc::vec::Vec;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer, const MEM: usize>(
+        memory: &[u8; MEM],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(memory)
     }
 
+    pub fn deserialize<'de, D: Deserializer<'de>, const MEM: usize>(
+        deserializer: D,
+    ) -> Result<[u8; MEM], D::Error> {
+        let bytes = Vec::<u8>::deserialize(deserializer)?;
+        let len = bytes.len();
+        bytes.try_into().map_err(|_| D::Error::invalid_length(len, &"one byte per memory address"))
+    }
 }
 
-pub struct Cpu {
-    /// Program counter (only 12 least significant bits used)
+/// Chip-8 interpreter with `MEM` bytes of memory. The standard 4 KB is the default; XO-CHIP
+/// programs can use up to 64 KB.
+pub struct Cpu<const MEM: usize = 4096> {
+    /// Program counter (wraps within memory)
     pc: u16,
 
-    /// Index register (only 12 least significant bits used)
+    /// Index register (all 16 bits, not masked; memory accesses through it wrap)
     index: u16,
 
     /// Register file
     reg: [u8; 16],
 
-    // TODO: Finish implementation of delay timer
+    /// Delay timer, decremented at 60 Hz by `tick_timers`
     delay_timer: u8,
 
-    // TODO: Finish implementation of sound timer
+    /// Sound timer, decremented at 60 Hz by `tick_timers`
     sound_timer: u8,
 
     /// Random access memory
-    memory: [u8; 4096],
+    memory: [u8; MEM],
+
+    /// Address programs are loaded at and execution starts from
+    start_address: u16,
+
+    /// Address of the font
+    font_base: u16,
+
+    /// Address of the big font, which follows the font unless a custom font was installed
+    big_font_base: u16,
 
     stack: Stack,
 
-    // TODO: Implement display
+    /// Hex keypad state, indexed by key
+    keys: [bool; 16],
+    /// Key events not yet applied to `keys`, one of which is applied before each cycle
+    key_events: VecDeque<KeyEvent>,
+    /// Scripted key events not yet queued, with the cycle count they're due at, in order
+    input_script: VecDeque<(u64, KeyEvent)>,
+
+    /// SUPER-CHIP RPL user flags, which programs use to persist data such as high scores
+    rpl: [u8; 8],
+
+    /// XO-CHIP audio pattern buffer, a 1-bit waveform of 128 samples
+    audio_pattern: [u8; 16],
+
+    /// XO-CHIP audio pattern playback pitch
+    audio_pitch: u8,
+
+    /// Monochrome display, 64x32 or 128x64 in SUPER-CHIP high resolution mode
+    display: Display,
+
+    /// Set by DRW and cleared by `tick_timers`
+    draw_occurred: bool,
+
+    /// Set by DRW when a lit pixel is turned off and cleared by `tick_timers`
+    collision_occurred: bool,
+
+    /// Set by `run_headless` to make DRW check for collisions without changing any pixels
+    headless: bool,
+
+    /// Interpreter-specific behavior options
+    quirks: Quirks,
+
+    /// Random number generator used by RND
+    rng: Box<dyn Rng>,
+
+    /// Number of instructions executed
+    cycles: u64,
+
+    /// Total cost of the instructions executed, in machine cycles
+    machine_cycles: u64,
+
+    /// Instructions per second executed by `advance`, or machine cycles per second if cycle
+    /// costs are enabled
+    clock_hz: u32,
+
+    /// Whether `advance` times instructions by their cost rather than one per cycle
+    cycle_costs_enabled: bool,
+
+    /// Machine cycles `advance` has been given but not yet spent. Negative when an expensive
+    /// instruction overran the time given.
+    cost_credit: i64,
+
+    /// Wall-clock time not yet turned into cycles and timer ticks by `advance`, in nanoseconds
+    /// multiplied by the respective frequency
+    cycle_remainder: u128,
+    tick_remainder: u128,
+
+    /// Which memory bytes have been written, by loading a ROM, writing the fonts or executing
+    /// instructions. Only checked in strict mode.
+    written: [bool; MEM],
+    /// Whether fetching an instruction from never-written memory is an error
+    strict: bool,
+
+    /// Number of times each kind of instruction executed, indexed by `Instruction::kind`, if
+    /// enabled
+    histogram: [u64; Instruction::KIND_COUNT],
+    histogram_enabled: bool,
+
+    /// States from before the most recent cycles along with which bytes had been written,
+    /// newest last, kept for `step_back` if rewind is enabled
+    rewind: VecDeque<(CpuState<MEM>, [bool; MEM])>,
+    rewind_capacity: usize,
+
+    /// Diagnostics recorded since they were last taken, if enabled
+    diagnostics: Vec<Diagnostic>,
+    diagnostics_enabled: bool,
+
+    /// Position within the current period of the buzzer's square wave, in units of
+    /// 1 / sample rate of a period
+    audio_phase: u32,
+
+    /// Optional callback for tracing executed instructions
+    trace_hook: Option<TraceHook>,
+
+    /// Optional callback that can pause or abort each cycle before it starts
+    pre_cycle_hook: Option<PreCycleHook>,
+}
+
+/// Standard Chip-8 interpreter with 4 KB of memory.
+pub type Chip8 = Cpu<4096>;
+
+impl<const MEM: usize> Default for Cpu<MEM> {
+    fn default() -> Self {
+        Self::new_sized()
+    }
+}
+
+/// Clones the complete machine state. The hooks can't be cloned, so the clone has none.
+impl<const MEM: usize> Clone for Cpu<MEM> {
+    fn clone(&self) -> Self {
+        Self {
+            pc: self.pc,
+            index: self.index,
+            reg: self.reg,
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer,
+            memory: self.memory,
+            start_address: self.start_address,
+            font_base: self.font_base,
+            big_font_base: self.big_font_base,
+            stack: self.stack.clone(),
+            keys: self.keys,
+            key_events: self.key_events.clone(),
+            input_script: self.input_script.clone(),
+            rpl: self.rpl,
+            audio_pattern: self.audio_pattern,
+            audio_pitch: self.audio_pitch,
+            display: self.display.clone(),
+            draw_occurred: self.draw_occurred,
+            collision_occurred: self.collision_occurred,
+            headless: self.headless,
+            quirks: self.quirks,
+            rng: self.rng.clone_box(),
+            cycles: self.cycles,
+            machine_cycles: self.machine_cycles,
+            clock_hz: self.clock_hz,
+            cycle_costs_enabled: self.cycle_costs_enabled,
+            cost_credit: self.cost_credit,
+            cycle_remainder: self.cycle_remainder,
+            tick_remainder: self.tick_remainder,
+            written: self.written,
+            strict: self.strict,
+            histogram: self.histogram,
+            histogram_enabled: self.histogram_enabled,
+            rewind: self.rewind.clone(),
+            rewind_capacity: self.rewind_capacity,
+            diagnostics: self.diagnostics.clone(),
+            diagnostics_enabled: self.diagnostics_enabled,
+            audio_phase: self.audio_phase,
+            trace_hook: None,
+            pre_cycle_hook: None,
+        }
+    }
+}
+
+/// Compares the machine state a program can observe or affect: registers, timers, memory, the
+/// stack, keys, RPL flags, the display, the font location and quirks. Bookkeeping such as the
+/// cycle count, clock rate, strict mode, the histogram and the random number generator's state
+/// is ignored.
+impl<const MEM: usize> PartialEq for Cpu<MEM> {
+    fn eq(&self, other: &Self) -> bool {
+        self.pc == other.pc
+            && self.index == other.index
+            && self.reg == other.reg
+            && self.delay_timer == other.delay_timer
+            && self.sound_timer == other.sound_timer
+            && self.memory == other.memory
+            && self.font_base == other.font_base
+            && self.big_font_base == other.big_font_base
+            && self.stack == other.stack
+            && self.keys == other.keys
+            && self.rpl == other.rpl
+            && self.audio_pattern == other.audio_pattern
+            && self.audio_pitch == other.audio_pitch
+            && self.display == other.display
+            && self.draw_occurred == other.draw_occurred
+            && self.collision_occurred == other.collision_occurred
+            && self.quirks == other.quirks
+    }
+}
+
+impl<const MEM: usize> Eq for Cpu<MEM> {}
+
+/// Shows the registers and other small state. Memory and the display are summarized by their
+/// sizes rather than dumped.
+impl<const MEM: usize> fmt::Debug for Cpu<MEM> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Cpu")
+            .field("pc", &format_args!("{:#06x}", self.pc))
+            .field("index", &format_args!("{:#06x}", self.index))
+            .field("reg", &self.reg)
+            .field("delay_timer", &self.delay_timer)
+            .field("sound_timer", &self.sound_timer)
+            .field("memory", &format_args!("[{MEM} bytes]"))
+            .field("stack", &self.stack)
+            .field("keys", &self.keys)
+            .field("rpl", &self.rpl)
+            .field("display_size", &self.display_size())
+            .field("draw_occurred", &self.draw_occurred)
+            .field("collision_occurred", &self.collision_occurred)
+            .field("quirks", &self.quirks)
+            .field("cycles", &self.cycles)
+            .finish_non_exhaustive()
+    }
 }
 
 impl Cpu {
     pub fn new() -> Self {
-        Self {
-            pc: 0x200,  // Most Chip-8 programs start at this address
+        Self::with_seed(0)
+    }
+
+    /// Create a new CPU with the random number generator seeded with `seed`.
+    pub fn with_seed(seed: u64) -> Self {
+        Self::sized_with_seed(seed)
+    }
+}
+
+impl<const MEM: usize> Cpu<MEM> {
+    /// Create a new CPU with `MEM` bytes of memory, e.g. `Cpu::<65536>::new_sized()`. `MEM`
+    /// must be between 4 KB and 64 KB.
+    pub fn new_sized() -> Self {
+        Self::sized_with_seed(0)
+    }
+
+    /// Create a new CPU with `MEM` bytes of memory and the random number generator seeded with
+    /// `seed`.
+    pub fn sized_with_seed(seed: u64) -> Self {
+        // Addresses are 16 bits, and the fonts and programs expect at least 4 KB
+        const { assert!(MEM >= 0x1000 && MEM <= 0x10000) };
+
+        let mut cpu = Self {
+            pc: PROGRAM_START_ADDRESS,
             index: 0,
             reg: [0; 16],
             delay_timer: 0,
             sound_timer: 0,
-            memory: [0; 4096],
+            memory: [0; MEM],
+            start_address: PROGRAM_START_ADDRESS,
+            font_base: FONT_BASE_ADDRESS,
+            big_font_base: FONT_BASE_ADDRESS + FONT.len() as u16,
             stack: Stack::new(),
+            keys: [false; 16],
+            key_events: VecDeque::new(),
+            input_script: VecDeque::new(),
+            rpl: [0; 8],
+            audio_pattern: [0; 16],
+            audio_pitch: DEFAULT_AUDIO_PITCH,
+            display: Display::new(),
+            draw_occurred: false,
+            collision_occurred: false,
+            headless: false,
+            quirks: Quirks::default(),
+            rng: Box::new(XorShift::new(seed)),
+            cycles: 0,
+            machine_cycles: 0,
+            clock_hz: DEFAULT_CLOCK_HZ,
+            cycle_costs_enabled: false,
+            cost_credit: 0,
+            cycle_remainder: 0,
+            tick_remainder: 0,
+            written: [false; MEM],
+            strict: false,
+            histogram: [0; Instruction::KIND_COUNT],
+            histogram_enabled: false,
+            rewind: VecDeque::new(),
+            rewind_capacity: 0,
+            diagnostics: Vec::new(),
+            diagnostics_enabled: false,
+            audio_phase: 0,
+            trace_hook: None,
+            pre_cycle_hook: None,
+        };
+        cpu.write_fonts();
+        cpu
+    }
+
+    /// Create a new CPU whose memory is a copy of `memory`, e.g. a captured image. Nothing is
+    /// written over it, so the image should include the font at the default font address if
+    /// the program uses it. Execution starts at 0x200 as usual.
+    pub fn from_memory(memory: [u8; MEM]) -> Self {
+        let mut cpu = Self::new_sized();
+        cpu.memory = memory;
+        cpu.mark_written(0, MEM);
+        cpu
+    }
+
+    /// Copy the font and big font into memory at their base addresses.
+    fn write_fonts(&mut self) {
+        let font_start = self.font_base as usize;
+        self.memory[font_start..font_start + FONT.len()].copy_from_slice(&FONT);
+        self.mark_written(font_start, FONT.len());
+        let big_font_start = self.big_font_base as usize;
+        self.memory[big_font_start..big_font_start + BIG_FONT.len()].copy_from_slice(&BIG_FONT);
+        self.mark_written(big_font_start, BIG_FONT.len());
+    }
+
+    /// Move the fonts to `base`, clearing their old location. Fails if the fonts would overlap
+    /// the program space starting at the program start address.
+    pub(crate) fn set_font_base(&mut self, base: u16) -> Result<(), LoadError> {
+        let font_end = base as usize + FONT.len() + BIG_FONT.len();
+        if font_end > self.start_address as usize {
+            return Err(LoadError::FontOverlapsProgram { font_base: base });
         }
+
+        let old_start = self.font_base as usize;
+        self.memory[old_start..old_start + FONT.len()].fill(0);
+        let old_big_start = self.big_font_base as usize;
+        self.memory[old_big_start..old_big_start + BIG_FONT.len()].fill(0);
+        self.font_base = base;
+        self.big_font_base = base + FONT.len() as u16;
+        self.write_fonts();
+        Ok(())
     }
 
-    pub fn cycle(&mut self) {
-        // Load instruction word from memory
-        let instr_lo = self.memory[self.pc as usize];
-        let instr_hi = self.memory[self.pc as usize + 1];
-        let instr_word = ((instr_hi as u16) << 8) | instr_lo as u16;
+    /// Install a custom hexadecimal font of 16 5-byte sprites at `base`, which `LD F, Vx` will
+    /// use from now on. The font may go anywhere in memory, including the program space. The
+    /// old font and the big font are left where they are.
+    pub fn set_font(&mut self, base: u16, data: &[u8]) -> Result<(), LoadError> {
+        if data.len() != FONT.len() {
+            return Err(LoadError::InvalidFontSize { size: data.len(), expected: FONT.len() });
+        }
+        let start = base as usize;
+        if start + data.len() > MEM {
+            return Err(LoadError::FontOutOfMemory { font_base: base });
+        }
 
-        // Decode instruction word
-        let instr = Instruction::decode(instr_word);
+        self.memory[start..start + data.len()].copy_from_slice(data);
+        self.mark_written(start, data.len());
+        self.font_base = base;
+        Ok(())
+    }
 
-        // Increment program counter
-        self.pc += 2;
+    /// Reset the CPU to its power-on state while keeping the contents of memory, so a loaded
+    /// program can be restarted. The RPL flags are also kept since they persist between runs.
+    pub fn reset(&mut self) {
+        self.pc = self.start_address;
+        self.index = 0;
+        self.reg = [0; 16];
+        self.delay_timer = 0;
+        self.sound_timer = 0;
+        self.stack = Stack::new();
+        self.keys = [false; 16];
+        self.key_events.clear();
+        self.input_script.clear();
+        self.audio_pattern = [0; 16];
+        self.audio_pitch = DEFAULT_AUDIO_PITCH;
+        self.display = Display::new();
+        self.draw_occurred = false;
+        self.collision_occurred = false;
+        self.cycles = 0;
+        self.machine_cycles = 0;
+        self.cost_credit = 0;
+        self.cycle_remainder = 0;
+        self.tick_remainder = 0;
+        self.histogram = [0; Instruction::KIND_COUNT];
+        self.rewind.clear();
+        self.diagnostics.clear();
+        self.audio_phase = 0;
+    }
 
-        use Instruction::*;
-        match instr {
-            Cls => {
-                // TODO: Implement display
-                todo!();
-            },
-            Ret => {
-                self.pc = self.stack.pop();
-            },
-            JpImm(addr) => {
-                self.pc = addr;
-            },
-            Call(addr) => {
-                self.stack.push(self.pc);
-                self.pc = addr;
-            },
-            SeImm(vx, imm) => {
-                if self.reg[vx as usize] == imm {
-                    self.pc += 2;
-                }
-            },
-            SneImm(vx, imm) => {
-                if self.reg[vx as usize] != imm {
-                    self.pc += 2;
-                }
-            },
-            SeReg(vx, vy) => {
-                if self.reg[vx as usize] == self.reg[vy as usize] {
-                    self.pc += 2;
-                }
-            },
-            LdImm(vx, imm) => {
-                self.reg[vx as usize] = imm;
-            },
-            AddImm(vx, imm) => {
-                (self.reg[vx as usize], _) = self.reg[vx as usize].overflowing_add(imm);
-            },
-            LdReg(vx, vy) => {
-                self.reg[vx as usize] = self.reg[vy as usize];
-            },
-            OrReg(vx, vy) => {
-                self.reg[vx as usize] |= self.reg[vy as usize];
-            },
-            AndReg(vx, vy) => {
-                self.reg[vx as usize] &= self.reg[vy as usize];
-            },
-            XorReg(vx, vy) => {
-                self.reg[vx as usize] ^= self.reg[vy as usize];
-            },
-            AddReg(vx, vy) => {
-                let carry;
-                (self.reg[vx as usize], carry) = self.reg[vx as usize].overflowing_add(self.reg[vy as usize]);
-                // Set flag register based on carry
-                self.reg[0xf] = if carry { 1 } else { 0 };
-            },
-            SubReg(vx, vy) => {
-                let borrow;
-                (self.reg[vx as usize], borrow) = self.reg[vx as usize].overflowing_sub(self.reg[vy as usize]);
-                // Set flag register based on borrow
-                self.reg[0xf] = if !borrow { 1 } else { 0 };
-            },
-            Shr(vx, _vy) => {
-                let carry;
-                (self.reg[vx as usize], carry) = self.reg[vx as usize].overflowing_shr(1);
-                // Set flag register based on carry
-                self.reg[0xf] = if carry { 1 } else { 0 };
-            },
-            Subn(vx, vy) => {
-                let borrow;
-                (self.reg[vx as usize], borrow) = self.reg[vy as usize].overflowing_sub(self.reg[vx as usize]);
-                // Set flag register based on borrow
-                self.reg[0xf] = if !borrow { 1 } else { 0 };
-            },
-            Shl(vx, _vy) => {
-                let carry;
-                (self.reg[vx as usize], carry) = self.reg[vx as usize].overflowing_shl(1);
-                self.reg[0xf] = if carry { 1 } else { 0 };
-            },
-            SneReg(vx, vy) => {
-                if self.reg[vx as usize] != self.reg[vy as usize] {
-                    self.pc += 2;
-                }
-            },
-            LdI(addr) => {
-                self.index = addr;
-            },
-            JpReg(addr) => {
-                self.pc = addr + self.reg[0] as u16;
-            },
-            Rnd(_vx, _imm) => {
-                // TODO: Implement random number generation
-                todo!();
-            },
-            Drw(_vx, _vy, _n) => {
-                // TODO: Implement draw functionality
-                todo!();
-            },
-            Skp(_vx) => {
-                // TODO: Implement keypress detection
-                todo!();
-            },
-            Sknp(_vx) => {
-                // TODO: Implement keypress detection
-                todo!();
-            },
-            LdRegDt(vx) => {
-                self.reg[vx as usize] = self.delay_timer;
-            },
-            LdRegK(_vx) => {
-                // TODO: implement keypress detection
-                todo!()
-            },
-            LdDtReg(vx) => {
-                self.delay_timer = self.reg[vx as usize]
-            },
-            LdStReg(vx) => {
-                self.sound_timer = self.reg[vx as usize]
-            },
-            AddI(vx) => {
-                self.index = self.index.wrapping_add(self.reg[vx as usize] as u16);
-            },
-            LdF(vx) => {
-                self.index = FONT_BASE_ADDRESS + (vx as u16 * FONT_CHAR_SIZE);
-            },
-            LdB(vx) => {
-                todo!()
-            },
-            LdMemReg(vx) => {
-                for i in 0..=vx as usize {
-                    self.memory[self.index as usize + i] = self.reg[i]
-                }
-            },
-            LdRegMem(vx) =>{
-                for i in 0..=vx as usize {
-                    self.reg[i] = self.memory[self.index as usize + i]
-                }
-            },
+    /// Zero memory from the program start address to the end, marking it unwritten, so that
+    /// nothing of a previous program is left behind.
+    pub(crate) fn clear_program_space(&mut self) {
+        let start = self.start_address as usize;
+        self.memory[start..].fill(0);
+        self.written[start..].fill(false);
+    }
+
+    /// Change the address programs are loaded at and execution starts from, moving PC there.
+    /// Fails if the address is outside memory.
+    pub(crate) fn set_start_address(&mut self, start: u16) -> Result<(), LoadError> {
+        if start as usize >= MEM {
+            return Err(LoadError::StartOutOfMemory { start });
+        }
+        self.start_address = start;
+        self.pc = start;
+        Ok(())
+    }
+
+    /// Copy a program into memory at the program start address, which is 0x200 unless set
+    /// with [`CpuBuilder::start_address`](crate::CpuBuilder::start_address). If the ROM has an
+    /// odd length, its last instruction word is padded with a zero low byte.
+    pub fn load_rom(&mut self, rom: &[u8]) -> Result<(), LoadError> {
+        let start = self.start_address as usize;
+        let max = self.memory.len() - start;
+        if rom.len() > max {
+            return Err(LoadError::RomTooLarge { size: rom.len(), max });
+        }
+
+        let end = start + rom.len();
+        self.memory[start..end].copy_from_slice(rom);
+        self.mark_written(start, rom.len());
+        if rom.len() % 2 == 1 && end < self.memory.len() {
+            self.memory[end] = 0;
+            self.mark_written(end, 1);
+        }
+        Ok(())
+    }
+
+    /// Read a ROM file and copy it into memory at the program start address, like
+    /// [`Cpu::load_rom`].
+    #[cfg(feature = "std")]
+    pub fn load_rom_file<P: AsRef<std::path::Path>>(&mut self, path: P) -> Result<(), LoadError> {
+        let rom = std::fs::read(path)?;
+        self.load_rom(&rom)
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
+
+    /// Instructions per second executed by [`Cpu::advance`]. Defaults to 700.
+    pub fn clock_hz(&self) -> u32 {
+        self.clock_hz
+    }
+
+    /// Change the number of instructions per second executed by [`Cpu::advance`], e.g. to
+    /// change the speed of a game while it runs. The timers always tick at 60 Hz.
+    pub fn set_clock_hz(&mut self, hz: u32) {
+        self.clock_hz = hz;
+    }
+
+    /// The display contents as a row-major slice of pixels, where `true` is a set pixel.
+    pub fn framebuffer(&self) -> &[bool] {
+        self.display.pixels()
+    }
+
+    /// The address of the next instruction to execute.
+    pub fn pc(&self) -> u16 {
+        self.pc
+    }
+
+    /// The general purpose registers V0-VF, including the flag register VF.
+    pub fn registers(&self) -> &[u8; 16] {
+        &self.reg
+    }
+
+    pub fn set_registers(&mut self, regs: [u8; 16]) {
+        self.reg = regs;
+    }
+
+    /// The return addresses of the active subroutine calls, from the outermost call to the
+    /// innermost.
+    pub fn call_stack(&self) -> &[u16] {
+        &self.stack.bytes[..self.stack.sp]
+    }
+
+    /// The SUPER-CHIP RPL user flags, so that a host can persist them between runs.
+    pub fn rpl_flags(&self) -> &[u8; 8] {
+        &self.rpl
+    }
 
-            #[allow(unreachable_patterns)]
-            _ => panic!("unimplemented instruction: {instr:?}"),
+    pub fn set_rpl_flags(&mut self, flags: [u8; 8]) {
+        self.rpl = flags;
+    }
+
+    /// Whether the pixel at (x, y) is set. Out of range coordinates return false.
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        self.display.pixel(x, y)
+    }
+
+    /// Set or unset the pixel at (x, y). Out of range coordinates are ignored.
+    pub fn set_pixel(&mut self, x: usize, y: usize, on: bool) {
+        self.display.set_pixel(x, y, on);
+    }
+
+    /// The contents of XO-CHIP display plane 0 or 1, laid out like [`Cpu::framebuffer`], which
+    /// is plane 0. Panics if `plane` is out of range.
+    pub fn plane_framebuffer(&self, plane: usize) -> &[bool] {
+        self.display.plane_pixels(plane)
+    }
+
+    /// Whether the display has changed since the last call, clearing the flag. A frontend can
+    /// use this to skip redrawing frames where nothing changed. The flag starts out set.
+    pub fn take_dirty(&mut self) -> bool {
+        self.display.take_dirty()
+    }
+
+    /// Width and height of the display in its active resolution.
+    pub fn display_size(&self) -> (usize, usize) {
+        (self.display.width(), self.display.height())
+    }
+
+    /// Render the display as text, one line per row, with `#` for set pixels and `.` for unset
+    /// ones. Useful for snapshot tests and quick terminal output.
+    pub fn render_ascii(&self) -> String {
+        self.display.render_ascii()
+    }
+
+    /// Number of instructions executed since the CPU was created or the count was reset.
+    pub fn cycle_count(&self) -> u64 {
+        self.cycles
+    }
+
+    /// Reset the instruction count and the machine cycle count to zero.
+    pub fn reset_cycle_count(&mut self) {
+        self.cycles = 0;
+        self.machine_cycles = 0;
+    }
+
+    /// Total cost in machine cycles of the instructions executed since the CPU was created or
+    /// the count was reset. Most instructions cost 1, while drawing and bulk memory operations
+    /// cost more.
+    pub fn elapsed_machine_cycles(&self) -> u64 {
+        self.machine_cycles
+    }
+
+    /// Make `advance` run `clock_hz` machine cycles per second, so that expensive instructions
+    /// take longer than cheap ones, instead of `clock_hz` instructions per second. Off by
+    /// default.
+    pub fn set_cycle_costs_enabled(&mut self, enabled: bool) {
+        self.cycle_costs_enabled = enabled;
+        self.cost_credit = 0;
+    }
+
+    pub fn cycle_costs_enabled(&self) -> bool {
+        self.cycle_costs_enabled
+    }
+
+    /// Up to `len` bytes of memory starting at `start`, cut short at the end of memory.
+    pub fn memory_slice(&self, start: u16, len: usize) -> &[u8] {
+        let start = (start as usize).min(self.memory.len());
+        let end = start.saturating_add(len).min(self.memory.len());
+        &self.memory[start..end]
+    }
+
+    /// Write a byte to memory. The address wraps within memory.
+    pub fn poke(&mut self, addr: u16, byte: u8) {
+        self.write_byte(addr as usize, byte);
+    }
+
+    /// Write sprite rows into memory starting at `addr`, one byte per row. Addresses wrap within
+    /// memory. Use [`Cpu::set_index`] to point I at the sprite for DRW.
+    pub fn load_sprite(&mut self, addr: u16, rows: &[u8]) {
+        for (i, &row) in rows.iter().enumerate() {
+            self.poke(Self::wrap_addr(addr as usize + i), row);
         }
     }
-}
\ No newline at end of file
+
+    /// Set the index register I.
+    pub fn set_index(&mut self, addr: u16) {
+        self.index = addr;
+    }
+
+    /// Record that `len` bytes of memory starting at `start` have been written.
+    fn mark_written(&mut self, start: usize, len: usize) {
+        self.written[start..start + len].fill(true);
+    }
+
+    /// Enable or disable strict mode, in which fetching an instruction from memory that was
+    /// never written returns [`ExecError::UninitializedRead`]. This catches a runaway PC early
+    /// instead of executing zeroed memory.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    /// Whether strict mode is enabled.
+    pub fn strict(&self) -> bool {
+        self.strict
+    }
+
+    /// Mark a key (0x0-0xF) as pressed. Other values are ignored.
+    pub fn press_key(&mut self, key: u8) {
+        if let Some(state) = self.keys.get_mut(key as usize) {
+            *state = true;
+        }
+    }
+
+    /// Mark a key (0x0-0xF) as released. Other values are ignored.
+    pub fn release_key(&mut self, key: u8) {
+        if let Some(state) = self.keys.get_mut(key as usize) {
+            *state = false;
+        }
+    }
+
+    /// Queue a key press or release. Queued events are applied one per cycle, in order, so a
+    /// key pressed and released between two frames is still seen by the program, e.g. by a
+    /// waiting `LD Vx, K`.
+    pub fn push_key_event(&mut self, event: KeyEvent) {
+        self.key_events.push_back(event);
+    }
+
+    /// Press or release a key as `event` says.
+    fn apply_key_event(&mut self, event: KeyEvent) {
+        match event {
+            KeyEvent::Pressed(key) => self.press_key(key),
+            KeyEvent::Released(key) => self.release_key(key),
+        }
+    }
+
+    /// Schedule key events for replay, replacing any earlier script. Each event is applied at
+    /// the start of the first cycle once [`Cpu::cycle_count`] has reached its cycle count, along
+    /// with any other events due by then, so input-dependent programs can be run
+    /// deterministically.
+    pub fn load_input_script(&mut self, mut script: Vec<(u64, KeyEvent)>) {
+        script.sort_by_key(|&(cycle, _)| cycle);
+        self.input_script = script.into();
+    }
+
+    /// Set the state of the whole keypad at once, indexed by key.
+    pub fn set_keys(&mut self, state: [bool; 16]) {
+        self.keys = state;
+    }
+
+    /// The state of the whole keypad as a bitmask, where bit i is set if key i is pressed.
+    pub fn keys_bitmask(&self) -> u16 {
+        self.keys.iter().enumerate().fold(0, |mask, (key, &pressed)| mask | (pressed as u16) << key)
+    }
+
+    /// Set the state of the whole keypad from a bitmask, where bit i is set if key i is pressed.
+    pub fn set_keys_bitmask(&mut self, mask: u16) {
+        self.keys = core::array::from_fn(|key| mask & (1 << key) != 0);
+    }
+
+    /// Press the key named by a hex digit character ('0'-'9', 'a'-'f' or 'A'-'F'). Other
+    /// characters are ignored.
+    pub fn press_hex(&mut self, c: char) {
+        if let Some(key) = c.to_digit(16) {
+            self.press_key(key as u8);
+        }
+    }
+
+    /// The lowest-numbered key that is currently pressed, if any.
+    pub fn first_pressed_key(&self) -> Option<u8> {
+        self.keys.iter().position(|&pressed| pressed).map(|key| key as u8)
+    }
+
+    /// Start or stop counting how many times each kind of instruction executes. Counting is off
+    /// by default.
+    pub fn set_histogram_enabled(&mut self, enabled: bool) {
+        self.histogram_enabled = enabled;
+    }
+
+    /// Reset every count in the opcode histogram to zero.
+    pub fn reset_histogram(&mut self) {
+        self.histogram = [0; Instruction::KIND_COUNT];
+    }
+
+    /// How many times each kind of instruction executed while the histogram was enabled,
+    /// indexed by [`Instruction::kind`]. [`Instruction::KIND_NAMES`] gives the name of each.
+    pub fn opcode_histogram(&self) -> &[u64; Instruction::KIND_COUNT] {
+        &self.histogram
+    }
+
+    /// Start or stop recording [`Diagnostic`]s. Recording is off by default.
+    pub fn set_diagnostics_enabled(&mut self, enabled: bool) {
+        self.diagnostics_enabled = enabled;
+    }
+
+    /// Return the diagnostics recorded since the last call, oldest first. At most 1024 are
+    /// kept between calls.
+    pub fn take_diagnostics(&mut self) -> Vec<Diagnostic> {
+        core::mem::take(&mut self.diagnostics)
+    }
+
+    /// Record a diagnostic if the jump at `pc` went to an odd address.
+    fn check_jump(&mut self, pc: u16) {
+        if self.pc & 1 == 0 {
+            return;
+        }
+        #[cfg(feature = "logging")]
+        log::warn!("jump at {pc:#06x} to odd address {:#06x}", self.pc);
+        if self.diagnostics_enabled && self.diagnostics.len() < MAX_DIAGNOSTICS {
+            self.diagnostics.push(Diagnostic::UnalignedJump { pc, target: self.pc });
+        }
+    }
+
+    /// Replace the random number generator used by RND.
+    pub fn set_rng(&mut self, rng: Box<dyn Rng>) {
+        self.rng = rng;
+    }
+
+    /// Install a callback that is invoked at the start of each cycle and decides whether it
+    /// executes, pauses or aborts. See [`Control`].
+    pub fn set_pre_cycle_hook(&mut self, hook: PreCycleHook) {
+        self.pre_cycle_hook = Some(hook);
+    }
+
+    pub fn clear_pre_cycle_hook(&mut self) {
+        self.pre_cycle_hook = None;
+    }
+
+    /// Install a callback that is invoked before each instruction is executed.
+    pub fn set_trace_hook(&mut self, hook: TraceHook) {
+        self.trace_hook = Some(hook);
+    }
+
+    pub fn clear_trace_hook(&mut self) {
+        self.trace_hook = None;
+    }
+
+    /// Whether a sprite has been drawn since the last call to [`Cpu::tick_timers`].
+    ///
+    /// With the `display_wait` quirk enabled, the first DRW in a frame stalls the CPU until the
+    /// next vertical blank: further calls to [`Cpu::cycle`] do nothing until `tick_timers` is
+    /// called. A host running a fixed number of cycles per 60 Hz frame followed by
+    /// `tick_timers` gets the correct behavior without any extra work, and can check this flag
+    /// to skip the rest of the frame's cycles early.
+    pub fn draw_occurred(&self) -> bool {
+        self.draw_occurred
+    }
+
+    pub fn delay_timer(&self) -> u8 {
+        self.delay_timer
+    }
+
+    pub fn set_delay_timer(&mut self, value: u8) {
+        self.delay_timer = value;
+    }
+
+    pub fn sound_timer(&self) -> u8 {
+        self.sound_timer
+    }
+
+    pub fn set_sound_timer(&mut self, value: u8) {
+        self.sound_timer = value;
+    }
+
+    /// Decrement the delay and sound timers. Should be called at 60 Hz, which also marks the
+    /// vertical blank for the `display_wait` quirk.
+    pub fn tick_timers(&mut self) {
+        self.delay_timer = self.delay_timer.saturating_sub(1);
+        self.sound_timer = self.sound_timer.saturating_sub(1);
+        self.draw_occurred = false;
+        self.collision_occurred = false;
+    }
+
+    /// Whether the buzzer should sound, which it does while the sound timer is nonzero.
+    pub fn is_beeping(&self) -> bool {
+        self.sound_timer > 0
+    }
+
+    /// Generate `samples` samples of the buzzer's output at `sample_rate` Hz: a square wave
+    /// while the sound timer is nonzero and silence otherwise. The wave's phase carries over
+    /// between calls so that consecutive buffers join up without clicks.
+    pub fn audio_samples(&mut self, sample_rate: u32, samples: usize) -> Vec<f32> {
+        if !self.is_beeping() {
+            return vec![0.0; samples];
+        }
+
+        let sample_rate = sample_rate.max(1);
+        (0..samples)
+            .map(|_| {
+                // High for the first half of each period and low for the second
+                let high = self.audio_phase < sample_rate / 2;
+                self.audio_phase = (self.audio_phase + BEEP_HZ) % sample_rate;
+                if high { BEEP_AMPLITUDE } else { -BEEP_AMPLITUDE }
+            })
+            .collect()
+    }
+
+    /// The XO-CHIP audio pattern buffer, set by `AUDIO`. Its 128 bits, most significant bit of
+    /// the first byte first, are a waveform to play in a loop while the sound timer is nonzero.
+    pub fn audio_pattern(&self) -> &[u8; 16] {
+        &self.audio_pattern
+    }
+
+    /// The XO-CHIP audio pitch, set by `PITCH`. The pattern plays back at
+    /// 4000 * 2^((pitch - 64) / 48) bits per second.
+    pub fn audio_pitch(&self) -> u8 {
+        self.audio_pitch
+    }
+
+    /// Capture the current machine state.
+    pub fn snapshot(&self) -> CpuState<MEM> {
+        CpuState {
+            pc: self.pc,
+            index: self.index,
+            reg: self.reg,
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer,
+            memory: self.memory,
+            stack: self.stack.bytes,
+            sp: self.stack.sp,
+            rpl: self.rpl,
+        }
+    }
+
+    /// Keep the state from before each of the last `capacity` cycles so that they can be undone
+    /// with [`Cpu::step_back`]. Each state holds a copy of memory, so the capacity is capped at
+    /// 1024. A capacity of 0 disables rewind, which is the default.
+    pub fn enable_rewind(&mut self, capacity: usize) {
+        self.rewind_capacity = capacity.min(MAX_REWIND_STATES);
+        while self.rewind.len() > self.rewind_capacity {
+            self.rewind.pop_front();
+        }
+    }
+
+    /// Undo the most recent cycle by restoring the state from before it, returning false if
+    /// there is none. Only the state captured by [`Cpu::snapshot`] is rewound; the display and
+    /// keys are left as they are.
+    pub fn step_back(&mut self) -> bool {
+        match self.rewind.pop_back() {
+            Some((state, written)) => {
+                self.restore(&state);
+                self.written = written;
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Restore a machine state previously captured with [`Cpu::snapshot`].
+    pub fn restore(&mut self, state: &CpuState<MEM>) {
+        self.pc = state.pc;
+        self.index = state.index;
+        self.reg = state.reg;
+        self.delay_timer = state.delay_timer;
+        self.sound_timer = state.sound_timer;
+        self.memory = state.memory;
+        // A snapshot doesn't record which bytes were written, so assume all of them were
+        self.written = [true; MEM];
+        self.stack.bytes = state.stack;
+        self.stack.sp = state.sp.min(self.stack.bytes.len());
+        self.rpl = state.rpl;
+    }
+
+    /// Read the byte at `addr`, wrapping within memory.
+    fn read_byte(&self, addr: usize) -> u8 {
+        self.memory[addr % MEM]
+    }
+
+    /// Write a byte at `addr`, wrapping within memory.
+    fn write_byte(&mut self, addr: usize, byte: u8) {
+        let addr = addr % MEM;
+        self.memory[addr] = byte;
+        self.mark_written(addr, 1);
+    }
+
+    /// Read the instruction word at `addr` (instructions are stored big-endian). At the last
+    /// byte of memory, the low byte wraps around to address 0.
+    fn read_word(&self, addr: u16) -> u16 {
+        let hi = self.read_byte(addr as usize);
+        let lo = self.read_byte(addr as usize + 1);
+        ((hi as u16) << 8) | lo as u16
+    }
+
+    /// Disassemble the instruction at `addr` as a listing line such as
+    /// `0x0204: D125  DRW V1, V2, 5`, with `???` in place of an invalid instruction.
+    pub fn trace_line(&self, addr: u16) -> String {
+        let addr = Self::wrap_addr(addr as usize);
+        let opcode = self.read_word(addr);
+        let next = self.read_word(Self::wrap_addr(addr as usize + 2));
+        match Instruction::try_decode_long(opcode, next) {
+            Ok((instr, _)) => format!("{addr:#06x}: {opcode:04X}  {instr}"),
+            Err(_) => format!("{addr:#06x}: {opcode:04X}  ???"),
+        }
+    }
+
+    /// Whether a skip instruction's condition holds in the current state, or `None` if
+    /// `instr` isn't a skip.
+    fn skip_condition(&self, instr: &Instruction) -> Option<bool> {
+        use Instruction::*;
+        let key_pressed = |vx: u8| self.keys[(self.reg[vx as usize] & 0xf) as usize];
+        match *instr {
+            SeImm(vx, imm) => Some(self.reg[vx as usize] == imm),
+            SneImm(vx, imm) => Some(self.reg[vx as usize] != imm),
+            SeReg(vx, vy) => Some(self.reg[vx as usize] == self.reg[vy as usize]),
+            SneReg(vx, vy) => Some(self.reg[vx as usize] != self.reg[vy as usize]),
+            Skp(vx) => Some(key_pressed(vx)),
+            Sknp(vx) => Some(!key_pressed(vx)),
+            _ => None,
+        }
+    }
+
+    /// Decode the instruction at PC without executing it or changing any state. Strict mode
+    /// doesn't apply, so this works even if the instruction's memory was never written.
+    pub fn peek(&self) -> Result<Instruction, DecodeError> {
+        let next = self.read_word(Self::wrap_addr(self.pc as usize + 2));
+        Instruction::try_decode_long(self.read_word(self.pc), next).map(|(instr, _)| instr)
+    }
+
+    /// If the instruction at PC is a skip (SE, SNE, SKP or SKNP), whether it would skip the
+    /// next instruction when executed in the current state. Returns `None` for any other
+    /// instruction.
+    pub fn peek_skip(&self) -> Option<bool> {
+        let instr = self.peek().ok()?;
+        self.skip_condition(&instr)
+    }
+
+    /// Whether the instruction at PC is a jump to itself, which many programs use to halt once
+    /// they are finished.
+    pub fn is_halted(&self) -> bool {
+        self.peek() == Ok(Instruction::JpImm(self.pc))
+    }
+
+    /// Write the flag register VF. Instructions that produce a result and a flag must call
+    /// this after writing the result, so that the flag wins when the result register is VF.
+    fn set_flag(&mut self, value: u8) {
+        self.reg[0xf] = value;
+    }
+
+    /// Wrap an address within memory.
+    fn wrap_addr(addr: usize) -> u16 {
+        (addr % MEM) as u16
+    }
+
+    /// Move the program counter to the next instruction, wrapping within memory.
+    fn advance_pc(&mut self) {
+        self.pc = Self::wrap_addr(self.pc as usize + 2);
+    }
+
+    /// Skip the instruction at PC, stepping over both words of a double-width instruction.
+    fn skip_next(&mut self) {
+        if Instruction::is_long(self.read_word(self.pc)) {
+            self.advance_pc();
+        }
+        self.advance_pc();
+    }
+
+    /// Run one 60 Hz frame: execute `cycles_per_frame` instructions, then tick the timers. With
+    /// the `display_wait` quirk enabled, the frame's remaining cycles are skipped after the
+    /// first draw. Returns what happened during the frame, for hosts that trigger sound or
+    /// haptics on these events.
+    pub fn run_frame(&mut self, cycles_per_frame: usize) -> Result<FrameEvents, ExecError> {
+        let was_beeping = self.is_beeping();
+        for _ in 0..cycles_per_frame {
+            if self.quirks.display_wait && self.draw_occurred {
+                break;
+            }
+            self.cycle()?;
+        }
+        let events = FrameEvents {
+            collision_occurred: self.collision_occurred,
+            draw_occurred: self.draw_occurred,
+            beep_started: !was_beeping && self.is_beeping(),
+        };
+        self.tick_timers();
+        Ok(events)
+    }
+
+    /// Run until the program halts by jumping to itself (see [`Cpu::is_halted`]), executing at
+    /// most `max_cycles` instructions. The timers aren't ticked. Fails with
+    /// [`ExecError::CycleLimit`] if the program is still running after `max_cycles`.
+    pub fn run_until_halt(&mut self, max_cycles: usize) -> Result<(), ExecError> {
+        for _ in 0..max_cycles {
+            if self.is_halted() {
+                return Ok(());
+            }
+            self.cycle()?;
+        }
+        if self.is_halted() {
+            Ok(())
+        } else {
+            Err(ExecError::CycleLimit { cycles: max_cycles })
+        }
+    }
+
+    /// Execute `cycles` instructions without rendering: DRW sets VF as usual but leaves the
+    /// pixels untouched, which speeds up test ROMs that only report results through registers.
+    /// Collisions are still detected against pixels drawn before the call. The timers aren't
+    /// ticked.
+    pub fn run_headless(&mut self, cycles: usize) -> Result<(), ExecError> {
+        self.headless = true;
+        let result = (0..cycles).try_for_each(|_| self.cycle());
+        self.headless = false;
+        result
+    }
+
+    /// Run as many cycles at the clock rate and 60 Hz timer ticks as fit in `elapsed` wall-clock
+    /// time, with the ticks spread evenly between the cycles. Time left over from a partial cycle
+    /// or tick is carried into the next call, so calling this every frame keeps both rates
+    /// exact.
+    ///
+    /// With cycle costs enabled, the clock rate counts machine cycles and each instruction uses
+    /// up its cost. An instruction that overruns the time given is paid back from the next call.
+    #[cfg(feature = "std")]
+    pub fn advance(&mut self, elapsed: std::time::Duration) -> Result<(), ExecError> {
+        self.cycle_remainder += elapsed.as_nanos() * self.clock_hz as u128;
+        self.tick_remainder += elapsed.as_nanos() * TIMER_HZ;
+        let cycles = self.cycle_remainder / NANOS_PER_SEC;
+        let ticks = self.tick_remainder / NANOS_PER_SEC;
+        self.cycle_remainder %= NANOS_PER_SEC;
+        self.tick_remainder %= NANOS_PER_SEC;
+
+        let mut ticked = 0;
+        if self.cycle_costs_enabled {
+            self.cost_credit += cycles as i64;
+            let mut spent = 0;
+            while self.cost_credit > 0 {
+                let before = self.machine_cycles;
+                self.cycle()?;
+                // A cycle spent waiting for the display still takes time
+                let cost = (self.machine_cycles - before).max(1);
+                self.cost_credit -= cost as i64;
+                spent += cost as u128;
+                while ticked < ticks && spent * ticks >= (ticked + 1) * cycles {
+                    self.tick_timers();
+                    ticked += 1;
+                }
+            }
+        } else {
+            for i in 1..=cycles {
+                self.cycle()?;
+                // Tick whenever the cycles run so far pass the next tick's share of the slice
+                while ticked < ticks && i * ticks >= (ticked + 1) * cycles {
+                    self.tick_timers();
+                    ticked += 1;
+                }
+            }
+        }
+        for _ in ticked..ticks {
+            self.tick_timers();
+        }
+        Ok(())
+    }
+
+    /// Fetch, decode and execute one instruction, as [`Cpu::fetch`], [`Cpu::decode`] and
+    /// [`Cpu::execute`] do step by step. If the instruction word is invalid or the instruction
+    /// can't be executed, an error is returned and PC is left pointing at it.
+    ///
+    /// The cycle also applies a queued key event, waits for the vertical blank with the
+    /// `display_wait` quirk, checks for reads of unwritten memory in strict mode and calls the
+    /// hooks, none of which the individual steps do.
+    pub fn cycle(&mut self) -> Result<(), ExecError> {
+        if let Some(hook) = &mut self.pre_cycle_hook {
+            match hook(self.pc) {
+                Control::Continue => {},
+                Control::Pause => return Ok(()),
+                Control::Abort => return Err(ExecError::Aborted { pc: self.pc }),
+            }
+        }
+
+        while let Some(&(cycle, event)) = self.input_script.front() {
+            if cycle > self.cycles {
+                break;
+            }
+            self.apply_key_event(event);
+            self.input_script.pop_front();
+        }
+
+        if let Some(event) = self.key_events.pop_front() {
+            self.apply_key_event(event);
+        }
+
+        // Wait for the vertical blank after a draw
+        if self.quirks.display_wait && self.draw_occurred {
+            return Ok(());
+        }
+
+        if self.strict {
+            // A double-width instruction's operand word is read as well
+            let len = if Instruction::is_long(self.read_word(self.pc)) { 4 } else { 2 };
+            let mut addrs = (0..len).map(|i| Self::wrap_addr(self.pc as usize + i));
+            if let Some(addr) = addrs.find(|&addr| !self.written[addr as usize]) {
+                return Err(ExecError::UninitializedRead { addr });
+            }
+        }
+
+        let rewind_state = (self.rewind_capacity > 0).then(|| (self.snapshot(), self.written));
+
+        // Load instruction word from memory, along with the operand word of a double-width
+        // instruction
+        let instr_pc = self.pc;
+        let instr_word = self.fetch();
+        let next_word = if Instruction::is_long(instr_word) { self.fetch() } else { 0 };
+
+        let Ok((instr, _)) = Instruction::try_decode_long(instr_word, next_word) else {
+            #[cfg(feature = "logging")]
+            log::warn!("invalid opcode {instr_word:#06x} at {instr_pc:#06x}");
+            self.pc = instr_pc;
+            return Err(ExecError::InvalidOpcode { opcode: instr_word, pc: instr_pc });
+        };
+
+        #[cfg(feature = "logging")]
+        log::trace!("{instr_pc:#06x}: {instr_word:04X}  {instr}");
+        if let Some(hook) = &mut self.trace_hook {
+            hook(instr_pc, instr_word, &instr);
+        }
+
+        self.execute(instr)?;
+
+        // Only keep the state once the instruction has executed, since a failed one can't be
+        // undone
+        if let Some(state) = rewind_state {
+            if self.rewind.len() == self.rewind_capacity {
+                self.rewind.pop_front();
+            }
+            self.rewind.push_back(state);
+        }
+        Ok(())
+    }
+
+    /// Read the instruction word at PC and advance PC past it. The word is read big-endian
+    /// from PC and PC + 1. Nothing requires PC to be even, so after a jump to an odd address
+    /// words are simply read from odd addresses. At the last byte of memory, the second byte
+    /// is read from address 0.
+    ///
+    /// A double-width instruction's operand is the word after it, read with a second fetch.
+    pub fn fetch(&mut self) -> u16 {
+        let word = self.read_word(self.pc);
+        self.advance_pc();
+        word
+    }
+
+    /// Decode an instruction word. Double-width instructions need their operand word too; see
+    /// [`Instruction::try_decode_long`].
+    pub fn decode(word: u16) -> Result<Instruction, DecodeError> {
+        Instruction::try_decode(word)
+    }
+
+    /// Execute an instruction that has already been fetched, so PC points past it. If the
+    /// instruction can't be executed, an error is returned and PC is moved back to point at it.
+    pub fn execute(&mut self, instr: Instruction) -> Result<(), ExecError> {
+        let instr_pc = Self::wrap_addr(self.pc as usize + MEM - instr.size() as usize);
+        let instr_cost = cost(&instr) as u64;
+        let kind = instr.kind();
+
+        use Instruction::*;
+        match instr {
+            Cls => {
+                self.display.clear_selected();
+            },
+            Ret => {
+                let Some(addr) = self.stack.pop() else {
+                    #[cfg(feature = "logging")]
+                    log::warn!("RET with an empty stack at {instr_pc:#06x}");
+                    self.pc = instr_pc;
+                    return Err(ExecError::StackUnderflow { pc: instr_pc });
+                };
+                self.pc = addr;
+            },
+            Sys(_) => {
+                // Machine code routines can't be run, so SYS is a no-op like in most interpreters
+            },
+            JpImm(addr) => {
+                self.pc = addr & 0x0fff;
+                self.check_jump(instr_pc);
+            },
+            Call(addr) => {
+                // PC may be out of range after restoring a corrupt state, so wrap the return
+                // address to keep it valid, which masks it to 12 bits with 4 KB of memory
+                if !self.stack.push(Self::wrap_addr(self.pc as usize)) {
+                    #[cfg(feature = "logging")]
+                    log::warn!("CALL with a full stack at {instr_pc:#06x}");
+                    self.pc = instr_pc;
+                    return Err(ExecError::StackOverflow { pc: instr_pc, depth: self.stack.bytes.len() });
+                }
+                self.pc = addr & 0x0fff;
+                self.check_jump(instr_pc);
+            },
+            SeImm(..) | SneImm(..) | SeReg(..) | SneReg(..) | Skp(..) | Sknp(..) => {
+                if self.skip_condition(&instr) == Some(true) {
+                    self.skip_next();
+                }
+            },
+            LdImm(vx, imm) => {
+                self.reg[vx as usize] = imm;
+            },
+            AddImm(vx, imm) => {
+                // Unlike 8xy4, 7xkk discards the carry and never affects VF
+                self.reg[vx as usize] = self.reg[vx as usize].wrapping_add(imm);
+            },
+            LdReg(vx, vy) => {
+                self.reg[vx as usize] = self.reg[vy as usize];
+            },
+            OrReg(vx, vy) => {
+                self.reg[vx as usize] |= self.reg[vy as usize];
+            },
+            AndReg(vx, vy) => {
+                self.reg[vx as usize] &= self.reg[vy as usize];
+            },
+            XorReg(vx, vy) => {
+                self.reg[vx as usize] ^= self.reg[vy as usize];
+            },
+            AddReg(vx, vy) => {
+                let carry;
+                (self.reg[vx as usize], carry) = self.reg[vx as usize].overflowing_add(self.reg[vy as usize]);
+                self.set_flag(carry as u8);
+            },
+            SubReg(vx, vy) => {
+                let borrow;
+                (self.reg[vx as usize], borrow) = self.reg[vx as usize].overflowing_sub(self.reg[vy as usize]);
+                self.set_flag(!borrow as u8);
+            },
+            Shr(vx, vy) => {
+                let src = if self.quirks.shift_uses_vy { vy } else { vx };
+                let value = self.reg[src as usize];
+                self.reg[vx as usize] = value >> 1;
+                // The carry is the bit shifted out
+                self.set_flag(value & 0x1);
+            },
+            Subn(vx, vy) => {
+                // Read both operands before writing anything, since either may be VF
+                let x = self.reg[vx as usize];
+                let y = self.reg[vy as usize];
+                let (result, borrow) = y.overflowing_sub(x);
+                self.reg[vx as usize] = result;
+                self.set_flag(!borrow as u8);
+            },
+            Shl(vx, vy) => {
+                let src = if self.quirks.shift_uses_vy { vy } else { vx };
+                let value = self.reg[src as usize];
+                self.reg[vx as usize] = value << 1;
+                // The carry is the bit shifted out
+                self.set_flag(value >> 7);
+            },
+            LdI(addr) => {
+                self.index = addr;
+            },
+            JpReg(addr) => {
+                // SUPER-CHIP treats the high nibble of the address as the offset register
+                let offset_reg = if self.quirks.jump_uses_vx { (addr >> 8) & 0xf } else { 0 };
+                // Jump targets are 12 bits, so a target past 0x0FFF wraps around to 0
+                self.pc = (addr + self.reg[offset_reg as usize] as u16) & 0x0fff;
+                self.check_jump(instr_pc);
+            },
+            Rnd(vx, imm) => {
+                self.reg[vx as usize] = self.rng.next_byte() & imm;
+            },
+            Drw(vx, vy, n) => {
+                let x = self.reg[vx as usize] as usize;
+                let y = self.reg[vy as usize] as usize;
+                let start = self.index as usize;
+                // In high resolution, n = 0 draws a 16x16 sprite of 32 bytes. In low resolution
+                // it draws nothing.
+                let large = n == 0 && self.display.is_hires();
+                let len = if large { 32 } else { n as usize };
+                // Each selected plane has its own sprite. Reads past the end of memory wrap
+                // around to address 0.
+                let planes = self.display.selected_planes().count_ones() as usize;
+                let mut buffer = [0; 32 * PLANE_COUNT];
+                let sprite = &mut buffer[..len * planes];
+                for (i, byte) in sprite.iter_mut().enumerate() {
+                    *byte = self.read_byte(start + i);
+                }
+                let wrap = self.quirks.sprite_wrapping;
+                let (collided, clipped) = if self.headless {
+                    self.display.test_sprite(x, y, sprite, if large { 2 } else { 1 }, wrap)
+                } else if large {
+                    self.display.draw_large_sprite(x, y, sprite, wrap)
+                } else {
+                    self.display.draw_sprite(x, y, sprite, wrap)
+                };
+                // Set flag register based on collision
+                let flag = if self.quirks.drw_count_clipped_rows && self.display.is_hires() {
+                    (collided + clipped) as u8
+                } else {
+                    (collided > 0) as u8
+                };
+                self.set_flag(flag);
+                self.draw_occurred = true;
+                self.collision_occurred |= collided > 0;
+            },
+            LdRegDt(vx) => {
+                self.reg[vx as usize] = self.delay_timer;
+            },
+            LdRegK(vx) => {
+                match self.first_pressed_key() {
+                    Some(key) => self.reg[vx as usize] = key,
+                    // Wait by executing this instruction again until a key is pressed
+                    None => self.pc = Self::wrap_addr(self.pc as usize + MEM - 2),
+                }
+            },
+            LdDtReg(vx) => {
+                self.delay_timer = self.reg[vx as usize]
+            },
+            LdStReg(vx) => {
+                self.sound_timer = self.reg[vx as usize]
+            },
+            AddI(vx) => {
+                let sum = self.index as u32 + self.reg[vx as usize] as u32;
+                self.index = self.index.wrapping_add(self.reg[vx as usize] as u16);
+                if self.quirks.add_index_sets_vf {
+                    self.set_flag((sum > 0x0fff) as u8);
+                }
+            },
+            LdF(vx) => {
+                let digit = (self.reg[vx as usize] & 0xf) as u16;
+                self.index = self.font_base + digit * FONT_CHAR_SIZE;
+            },
+            LdHf(vx) => {
+                // Only the digits 0-9 have big font sprites
+                let digit = (self.reg[vx as usize] % 10) as u16;
+                self.index = self.big_font_base + digit * BIG_FONT_CHAR_SIZE;
+            },
+            LdILong(addr) => {
+                self.index = addr;
+            },
+            Plane(n) => {
+                self.display.select_planes(n);
+            },
+            Audio => {
+                self.audio_pattern = core::array::from_fn(|i| self.read_byte(self.index as usize + i));
+            },
+            Pitch(vx) => {
+                self.audio_pitch = self.reg[vx as usize];
+            },
+            LdB(vx) => {
+                let value = self.reg[vx as usize];
+                let i = self.index as usize;
+                self.write_byte(i, value / 100);
+                self.write_byte(i + 1, (value / 10) % 10);
+                self.write_byte(i + 2, value % 10);
+            },
+            LdMemReg(vx) => {
+                // Writes past the end of memory wrap around to address 0
+                for i in 0..=vx as usize {
+                    self.write_byte(self.index as usize + i, self.reg[i]);
+                }
+                if self.quirks.memory_increments_index {
+                    self.index = self.index.wrapping_add(vx as u16 + 1);
+                }
+            },
+            LdRegMem(vx) =>{
+                for i in 0..=vx as usize {
+                    self.reg[i] = self.read_byte(self.index as usize + i);
+                }
+                if self.quirks.memory_increments_index {
+                    self.index = self.index.wrapping_add(vx as u16 + 1);
+                }
+            },
+
+            Low => {
+                self.display.set_hires(false);
+            },
+            High => {
+                self.display.set_hires(true);
+            },
+            ScrollDown(n) => {
+                self.display.scroll_down(n as usize);
+            },
+            ScrollRight => {
+                self.display.scroll_right(4);
+            },
+            ScrollLeft => {
+                self.display.scroll_left(4);
+            },
+
+            LdRplReg(vx) => {
+                // There are only 8 RPL flags
+                let count = (vx as usize).min(7) + 1;
+                self.rpl[..count].copy_from_slice(&self.reg[..count]);
+            },
+            LdRegRpl(vx) => {
+                let count = (vx as usize).min(7) + 1;
+                self.reg[..count].copy_from_slice(&self.rpl[..count]);
+            },
+
+        }
+
+        // Only count instructions that executed successfully
+        self.cycles += 1;
+        self.machine_cycles += instr_cost;
+        if self.histogram_enabled {
+            self.histogram[kind] += 1;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Write instruction words into memory starting at 0x200.
+    fn load_words(cpu: &mut Cpu, words: &[u16]) {
+        for (i, word) in words.iter().enumerate() {
+            cpu.memory[0x200 + 2 * i] = (word >> 8) as u8;
+            cpu.memory[0x200 + 2 * i + 1] = *word as u8;
+        }
+    }
+
+    #[test]
+    fn jp_imm_test() {
+        let mut cpu = Cpu::new();
+        load_words(&mut cpu, &[0x1234]);
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.pc, 0x234);
+    }
+
+    #[test]
+    fn se_sne_imm_test() {
+        // Each pair is (instruction, whether it skips) with V1 = 0x10
+        for (word, skips) in [(0x3110, true), (0x3111, false), (0x4110, false), (0x4111, true)] {
+            let mut cpu = Cpu::new();
+            cpu.reg[1] = 0x10;
+            load_words(&mut cpu, &[word]);
+            cpu.cycle().unwrap();
+            assert_eq!(cpu.pc, if skips { 0x204 } else { 0x202 }, "{word:04X}");
+        }
+    }
+
+    #[test]
+    fn se_sne_reg_test() {
+        // V1 = V2 and V1 != V3
+        for (word, skips) in [(0x5120, true), (0x5130, false), (0x9120, false), (0x9130, true)] {
+            let mut cpu = Cpu::new();
+            cpu.reg[1] = 0x42;
+            cpu.reg[2] = 0x42;
+            cpu.reg[3] = 0x43;
+            load_words(&mut cpu, &[word]);
+            cpu.cycle().unwrap();
+            assert_eq!(cpu.pc, if skips { 0x204 } else { 0x202 }, "{word:04X}");
+        }
+    }
+
+    #[test]
+    fn ld_test() {
+        let mut cpu = Cpu::new();
+        load_words(&mut cpu, &[0x6A42, 0x8BA0, 0xA123]);
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.reg[0xa], 0x42);
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.reg[0xb], 0x42);
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.index, 0x123);
+        assert_eq!(cpu.pc, 0x206);
+    }
+
+    #[test]
+    fn logic_test() {
+        for (word, result) in [(0x8011, 0b1110), (0x8012, 0b1000), (0x8013, 0b0110)] {
+            let mut cpu = Cpu::new();
+            cpu.reg[0] = 0b1100;
+            cpu.reg[1] = 0b1010;
+            cpu.reg[0xf] = 0x55;
+            load_words(&mut cpu, &[word]);
+            cpu.cycle().unwrap();
+            assert_eq!(cpu.reg[0], result, "{word:04X}");
+            assert_eq!(cpu.reg[1], 0b1010);
+            assert_eq!(cpu.reg[0xf], 0x55);
+        }
+    }
+
+    #[test]
+    fn add_reg_carry_test() {
+        let mut cpu = Cpu::new();
+        cpu.reg[0] = 0xf0;
+        cpu.reg[1] = 0x0f;
+        cpu.reg[2] = 0x11;
+        load_words(&mut cpu, &[0x8014, 0x8024]);
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.reg[0], 0xff);
+        assert_eq!(cpu.reg[0xf], 0);
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.reg[0], 0x10);
+        assert_eq!(cpu.reg[0xf], 1);
+    }
+
+    #[test]
+    fn sub_reg_borrow_test() {
+        let mut cpu = Cpu::new();
+        cpu.reg[0] = 0x10;
+        cpu.reg[1] = 0x10;
+        cpu.reg[2] = 0x01;
+        load_words(&mut cpu, &[0x8015, 0x8025]);
+        // Equal operands don't borrow
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.reg[0], 0);
+        assert_eq!(cpu.reg[0xf], 1);
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.reg[0], 0xff);
+        assert_eq!(cpu.reg[0xf], 0);
+    }
+
+    #[test]
+    fn timers_test() {
+        let mut cpu = Cpu::new();
+        cpu.reg[1] = 30;
+        cpu.reg[2] = 20;
+        load_words(&mut cpu, &[0xF115, 0xF218, 0xF307]);
+        cpu.cycle().unwrap();
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.delay_timer, 30);
+        assert_eq!(cpu.sound_timer, 20);
+        cpu.tick_timers();
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.reg[3], 29);
+    }
+
+    #[test]
+    fn ld_b_test() {
+        let mut cpu = Cpu::new();
+        cpu.reg[5] = 254;
+        cpu.index = 0x300;
+        load_words(&mut cpu, &[0xF533]);
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.memory[0x300..0x303], [2, 5, 4]);
+        assert_eq!(cpu.index, 0x300);
+    }
+
+    #[test]
+    fn ld_mem_reg_test() {
+        let mut cpu = Cpu::new();
+        cpu.reg = core::array::from_fn(|i| i as u8 + 1);
+        cpu.index = 0x300;
+        load_words(&mut cpu, &[0xF355]);
+        cpu.cycle().unwrap();
+        // V0 through V3 inclusive are stored and the index is left alone
+        assert_eq!(cpu.memory[0x300..0x305], [1, 2, 3, 4, 0]);
+        assert_eq!(cpu.index, 0x300);
+    }
+
+    #[test]
+    fn ld_reg_mem_test() {
+        let mut cpu = Cpu::new();
+        cpu.memory[0x300..0x304].copy_from_slice(&[9, 8, 7, 6]);
+        cpu.reg[3] = 0xaa;
+        cpu.index = 0x300;
+        load_words(&mut cpu, &[0xF265]);
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.reg[..4], [9, 8, 7, 0xaa]);
+        assert_eq!(cpu.index, 0x300);
+    }
+
+    #[test]
+    fn add_i_wrap_test() {
+        let mut cpu = Cpu::new();
+        cpu.reg[0] = 1;
+        cpu.reg[1] = 2;
+        cpu.index = 0x0fff;
+        load_words(&mut cpu, &[0xF01E, 0xF11E]);
+        // The index register is 16 bits wide, so it isn't confined to 12 bits
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.index, 0x1000);
+        cpu.index = 0xffff;
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.index, 0x0001);
+    }
+
+    #[test]
+    fn ld_f_test() {
+        let mut cpu = Cpu::new();
+        cpu.reg[4] = 0x1b;
+        load_words(&mut cpu, &[0xF429]);
+        cpu.cycle().unwrap();
+        // Only the low nibble selects the digit
+        assert_eq!(cpu.index, FONT_BASE_ADDRESS + 0xb * 5);
+    }
+
+    #[test]
+    fn drw_test() {
+        let mut cpu = Cpu::new();
+        cpu.reg[0] = 2;
+        cpu.reg[1] = 3;
+        cpu.index = 0x300;
+        cpu.memory[0x300] = 0b1000_0001;
+        load_words(&mut cpu, &[0xD011, 0xD011]);
+        cpu.cycle().unwrap();
+        assert!(cpu.pixel(2, 3) && cpu.pixel(9, 3) && !cpu.pixel(3, 3));
+        assert_eq!(cpu.reg[0xf], 0);
+        // Drawing again erases the sprite and reports a collision
+        cpu.cycle().unwrap();
+        assert!(!cpu.pixel(2, 3) && !cpu.pixel(9, 3));
+        assert_eq!(cpu.reg[0xf], 1);
+    }
+
+    #[test]
+    fn cls_test() {
+        let mut cpu = Cpu::new();
+        cpu.set_pixel(5, 5, true);
+        load_words(&mut cpu, &[0x00E0]);
+        cpu.cycle().unwrap();
+        assert!(!cpu.pixel(5, 5));
+    }
+
+    #[test]
+    fn jp_reg_v0_test() {
+        let mut cpu = Cpu::new();
+        load_words(&mut cpu, &[0xB320]);
+        cpu.reg[0] = 0x01;
+        cpu.reg[3] = 0x05;
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.pc, 0x321);
+    }
+
+    #[test]
+    fn jp_reg_vx_quirk_test() {
+        let mut cpu = Cpu::new();
+        cpu.set_quirks(Quirks { jump_uses_vx: true, ..Quirks::default() });
+        load_words(&mut cpu, &[0xB320]);
+        cpu.reg[0] = 0x01;
+        cpu.reg[3] = 0x05;
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.pc, 0x325);
+    }
+
+    #[test]
+    fn add_i_leaves_vf_test() {
+        let mut cpu = Cpu::new();
+        load_words(&mut cpu, &[0xF11E]);
+        cpu.index = 0x0ffe;
+        cpu.reg[1] = 0x03;
+        cpu.reg[0xf] = 0x55;
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.index, 0x1001);
+        assert_eq!(cpu.reg[0xf], 0x55);
+    }
+
+    #[test]
+    fn add_i_sets_vf_quirk_test() {
+        let mut cpu = Cpu::new();
+        cpu.set_quirks(Quirks { add_index_sets_vf: true, ..Quirks::default() });
+        load_words(&mut cpu, &[0xF11E, 0xF11E]);
+        cpu.index = 0x0ffc;
+        cpu.reg[1] = 0x03;
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.index, 0x0fff);
+        assert_eq!(cpu.reg[0xf], 0);
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.index, 0x1002);
+        assert_eq!(cpu.reg[0xf], 1);
+    }
+
+    #[test]
+    fn tick_timers_test() {
+        let mut cpu = Cpu::new();
+        cpu.delay_timer = 2;
+        cpu.sound_timer = 1;
+        cpu.tick_timers();
+        assert_eq!((cpu.delay_timer, cpu.sound_timer), (1, 0));
+        cpu.tick_timers();
+        assert_eq!((cpu.delay_timer, cpu.sound_timer), (0, 0));
+    }
+
+    #[test]
+    fn timer_accessors_test() {
+        let mut cpu = Cpu::new();
+        // LD DT, V0 and LD ST, V1
+        load_words(&mut cpu, &[0x6030, 0x6105, 0xF015, 0xF118]);
+        for _ in 0..4 {
+            cpu.cycle().unwrap();
+        }
+        assert_eq!((cpu.delay_timer(), cpu.sound_timer()), (0x30, 5));
+        cpu.tick_timers();
+        assert_eq!((cpu.delay_timer(), cpu.sound_timer()), (0x2f, 4));
+
+        cpu.set_delay_timer(7);
+        cpu.set_sound_timer(0);
+        assert_eq!((cpu.delay_timer, cpu.sound_timer), (7, 0));
+        assert!(!cpu.is_beeping());
+    }
+
+    #[test]
+    fn display_wait_quirk_test() {
+        let mut cpu = Cpu::new();
+        cpu.set_quirks(Quirks { display_wait: true, ..Quirks::default() });
+        load_words(&mut cpu, &[0xD001, 0x6005]);
+        cpu.cycle().unwrap();
+        assert!(cpu.draw_occurred());
+
+        // The CPU is stalled until the next frame
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.pc, 0x202);
+        assert_eq!(cpu.reg[0], 0);
+
+        cpu.tick_timers();
+        assert!(!cpu.draw_occurred());
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.reg[0], 5);
+    }
+
+    /// Draw an 8x1 sprite at (60, 0) and return the first row of the screen.
+    fn draw_at_right_edge(quirks: Quirks) -> String {
+        let mut cpu = Cpu::new();
+        cpu.set_quirks(quirks);
+        load_words(&mut cpu, &[0x603C, 0x6100, 0xA300, 0xD011]);
+        cpu.memory[0x300] = 0xff;
+        for _ in 0..4 {
+            cpu.cycle().unwrap();
+        }
+        cpu.render_ascii().lines().next().unwrap().to_string()
+    }
+
+    #[test]
+    fn sprite_clipping_test() {
+        let row = draw_at_right_edge(Quirks::default());
+        assert_eq!(row, format!("{}####", ".".repeat(60)));
+    }
+
+    #[test]
+    fn sprite_wrapping_quirk_test() {
+        let row = draw_at_right_edge(Quirks { sprite_wrapping: true, ..Quirks::default() });
+        assert_eq!(row, format!("####{}####", ".".repeat(56)));
+    }
+
+    #[test]
+    fn hires_test() {
+        let mut cpu = Cpu::new();
+        // Switch to high resolution and draw the font sprite for 0 at (100, 50)
+        load_words(&mut cpu, &[0x00FF, 0x6064, 0x6132, 0x6200, 0xF229, 0xD015, 0x00FE]);
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.display_size(), (128, 64));
+        assert_eq!(cpu.framebuffer().len(), 128 * 64);
+
+        for _ in 0..5 {
+            cpu.cycle().unwrap();
+        }
+        let ascii = cpu.render_ascii();
+        let lines: Vec<&str> = ascii.lines().collect();
+        assert_eq!(lines.len(), 64);
+        assert!(lines.iter().all(|line| line.len() == 128));
+        assert_eq!(&lines[50][100..104], "####");
+        assert_eq!(&lines[51][100..104], "#..#");
+        assert!(cpu.framebuffer()[54 * 128 + 103]);
+
+        // Switching back to low resolution clears the display
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.display_size(), (64, 32));
+        assert!(cpu.framebuffer().iter().all(|&pixel| !pixel));
+    }
+
+    #[test]
+    fn scroll_test() {
+        let mut cpu = Cpu::new();
+        // Draw the font sprite for 0 at (0, 0), then scroll down 2, right 4, and left 4
+        load_words(&mut cpu, &[0x6000, 0xF029, 0xD005, 0x00C2, 0x00FB, 0x00FC]);
+        for _ in 0..4 {
+            cpu.cycle().unwrap();
+        }
+        let ascii = cpu.render_ascii();
+        let lines: Vec<&str> = ascii.lines().collect();
+        assert_eq!(&lines[0][..8], "........");
+        assert_eq!(&lines[2][..8], "####....");
+        assert_eq!(&lines[6][..8], "####....");
+
+        cpu.cycle().unwrap();
+        let ascii = cpu.render_ascii();
+        let lines: Vec<&str> = ascii.lines().collect();
+        assert_eq!(&lines[2][..8], "....####");
+        assert_eq!(&lines[3][..8], "....#..#");
+
+        cpu.cycle().unwrap();
+        let ascii = cpu.render_ascii();
+        let lines: Vec<&str> = ascii.lines().collect();
+        assert_eq!(&lines[2][..8], "####....");
+        assert_eq!(cpu.framebuffer().iter().filter(|&&pixel| pixel).count(), 14);
+    }
+
+    #[test]
+    fn rpl_flags_test() {
+        let mut cpu = Cpu::new();
+        // Save V0-V2 to the RPL flags, clobber them, then restore them
+        load_words(&mut cpu, &[0xF275, 0x6000, 0x6100, 0x6200, 0xF285]);
+        cpu.reg[..3].copy_from_slice(&[0x11, 0x22, 0x33]);
+        cpu.reg[3] = 0x44;
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.rpl_flags()[..4], [0x11, 0x22, 0x33, 0]);
+
+        for _ in 0..3 {
+            cpu.cycle().unwrap();
+        }
+        assert_eq!(cpu.reg[..3], [0, 0, 0]);
+
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.reg[..4], [0x11, 0x22, 0x33, 0x44]);
+    }
+
+    #[test]
+    fn rpl_flags_clamp_test() {
+        let mut cpu = Cpu::new();
+        load_words(&mut cpu, &[0xFF75]);
+        cpu.reg = [0xaa; 16];
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.rpl_flags(), &[0xaa; 8]);
+    }
+
+    #[test]
+    fn big_font_test() {
+        let mut cpu = Cpu::new();
+        load_words(&mut cpu, &[0x6307, 0xF330]);
+        cpu.cycle().unwrap();
+        cpu.cycle().unwrap();
+        let i = cpu.index as usize;
+        assert_eq!(cpu.memory[i..i + 10], [0xFF, 0xFF, 0x03, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x60, 0x60]);
+    }
+
+    #[test]
+    fn cycle_count_test() {
+        let mut cpu = Cpu::new();
+        load_words(&mut cpu, &[0x6001, 0x6102, 0x8014, 0x7003, 0xA300]);
+        for _ in 0..5 {
+            cpu.cycle().unwrap();
+        }
+        assert_eq!(cpu.cycle_count(), 5);
+
+        cpu.reset_cycle_count();
+        assert_eq!(cpu.cycle_count(), 0);
+    }
+
+    #[test]
+    fn failed_cycle_count_test() {
+        let mut cpu = Cpu::new();
+        cpu.set_histogram_enabled(true);
+        load_words(&mut cpu, &[0x6001, 0x00EE]);
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.cycle(), Err(ExecError::StackUnderflow { pc: 0x202 }));
+        assert_eq!(cpu.cycle_count(), 1);
+        assert_eq!(cpu.elapsed_machine_cycles(), 1);
+        assert_eq!(cpu.opcode_histogram()[Instruction::Ret.kind()], 0);
+    }
+
+    #[test]
+    fn reset_test() {
+        let mut cpu = Cpu::new();
+        load_words(&mut cpu, &[0x6005, 0xA300, 0x2208, 0x0000, 0xF015, 0x6000, 0xD015]);
+        for _ in 0..5 {
+            cpu.cycle().unwrap();
+        }
+        assert_ne!(cpu.pc, 0x200);
+
+        cpu.reset();
+        assert_eq!(cpu.pc, 0x200);
+        assert_eq!(cpu.reg, [0; 16]);
+        assert_eq!(cpu.index, 0);
+        assert_eq!(cpu.delay_timer, 0);
+        assert_eq!(cpu.stack.sp, 0);
+        assert_eq!(cpu.cycle_count(), 0);
+        assert_eq!(cpu.memory[0x200..0x204], [0x60, 0x05, 0xA3, 0x00]);
+        assert_eq!(cpu.memory[0x100..0x105], FONT[..5]);
+    }
+
+    #[test]
+    fn trace_hook_test() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let mut cpu = Cpu::new();
+        load_words(&mut cpu, &[0x6001, 0x2206, 0x1204, 0x7001, 0x00EE]);
+        let trace = Rc::new(RefCell::new(Vec::new()));
+        let hook_trace = Rc::clone(&trace);
+        cpu.set_trace_hook(Box::new(move |pc, opcode, instr| {
+            hook_trace.borrow_mut().push((pc, opcode, format!("{instr:?}")));
+        }));
+
+        for _ in 0..4 {
+            cpu.cycle().unwrap();
+        }
+        assert_eq!(*trace.borrow(), [
+            (0x200, 0x6001, "LdImm(0, 1)".to_string()),
+            (0x202, 0x2206, "Call(518)".to_string()),
+            (0x206, 0x7001, "AddImm(0, 1)".to_string()),
+            (0x208, 0x00EE, "Ret".to_string()),
+        ]);
+
+        cpu.clear_trace_hook();
+        cpu.cycle().unwrap();
+        assert_eq!(trace.borrow().len(), 4);
+    }
+
+    #[test]
+    fn pc_wrap_test() {
+        let mut cpu = Cpu::new();
+        cpu.pc = 0x0ffe;
+        cpu.memory[0xffe..].copy_from_slice(&[0x60, 0x05]);
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.pc, 0x000);
+    }
+
+    #[test]
+    fn last_byte_pc_test() {
+        let mut cpu = Cpu::new();
+        cpu.pc = 0x0fff;
+        cpu.memory[0xfff] = 0x60;
+        cpu.memory[0x000] = 0x07;
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.reg[0], 0x07);
+        assert_eq!(cpu.pc, 0x001);
+
+        // In strict mode the wrapped byte must have been written too
+        let mut cpu = Cpu::new();
+        cpu.set_strict(true);
+        cpu.pc = 0x0fff;
+        cpu.poke(0xfff, 0x60);
+        assert_eq!(cpu.cycle(), Err(ExecError::UninitializedRead { addr: 0x000 }));
+        cpu.poke(0x000, 0x07);
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.reg[0], 0x07);
+    }
+
+    #[test]
+    fn skip_wrap_test() {
+        let mut cpu = Cpu::new();
+        cpu.pc = 0x0ffc;
+        cpu.memory[0xffc..0xffe].copy_from_slice(&[0x30, 0x00]);
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.pc, 0x000);
+    }
+
+    #[test]
+    fn shift_test() {
+        let mut cpu = Cpu::new();
+        load_words(&mut cpu, &[0x8016, 0x801E]);
+        cpu.reg[0] = 0x81;
+        cpu.reg[1] = 0x40;
+        cpu.cycle().unwrap();
+        assert_eq!((cpu.reg[0], cpu.reg[0xf]), (0x40, 1));
+        cpu.cycle().unwrap();
+        assert_eq!((cpu.reg[0], cpu.reg[0xf]), (0x80, 0));
+    }
+
+    #[test]
+    fn shift_uses_vy_quirk_test() {
+        let mut cpu = Cpu::new();
+        cpu.set_quirks(Quirks { shift_uses_vy: true, ..Quirks::default() });
+        load_words(&mut cpu, &[0x8016, 0x801E]);
+        cpu.reg[0] = 0x81;
+        cpu.reg[1] = 0x40;
+        cpu.cycle().unwrap();
+        assert_eq!((cpu.reg[0], cpu.reg[0xf]), (0x20, 0));
+        cpu.cycle().unwrap();
+        assert_eq!((cpu.reg[0], cpu.reg[0xf]), (0x80, 0));
+    }
+
+    #[test]
+    fn shift_same_register_test() {
+        // With VX = VY both modes shift the single register in place
+        for shift_uses_vy in [false, true] {
+            let mut cpu = Cpu::new();
+            cpu.set_quirks(Quirks { shift_uses_vy, ..Quirks::default() });
+            load_words(&mut cpu, &[0x8336, 0x833E, 0x833E]);
+            cpu.reg[3] = 0x83;
+            cpu.cycle().unwrap();
+            assert_eq!((cpu.reg[3], cpu.reg[0xf]), (0x41, 1));
+            cpu.cycle().unwrap();
+            assert_eq!((cpu.reg[3], cpu.reg[0xf]), (0x82, 0));
+            cpu.cycle().unwrap();
+            assert_eq!((cpu.reg[3], cpu.reg[0xf]), (0x04, 1));
+        }
+    }
+
+    #[test]
+    fn memory_increments_index_quirk_test() {
+        let mut cpu = Cpu::new();
+        load_words(&mut cpu, &[0xF255, 0xF165]);
+        cpu.index = 0x300;
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.index, 0x300);
+
+        cpu.set_quirks(Quirks { memory_increments_index: true, ..Quirks::default() });
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.index, 0x302);
+    }
+
+    #[test]
+    fn pixel_test() {
+        let mut cpu = Cpu::new();
+        cpu.set_pixel(10, 20, true);
+        assert!(cpu.pixel(10, 20));
+        assert!(cpu.framebuffer()[20 * 64 + 10]);
+        assert!(!cpu.pixel(11, 20));
+
+        cpu.set_pixel(10, 20, false);
+        assert!(!cpu.pixel(10, 20));
+
+        // Out of range pixels are ignored
+        cpu.set_pixel(64, 0, true);
+        cpu.set_pixel(0, 32, true);
+        assert!(!cpu.pixel(64, 0));
+        assert!(!cpu.pixel(0, 32));
+        assert!(cpu.framebuffer().iter().all(|&pixel| !pixel));
+    }
+
+    #[test]
+    fn set_pixel_collision_test() {
+        let mut cpu = Cpu::new();
+        // Draw the font sprite for 0 at (0, 0) over a pixel set by the host
+        cpu.set_pixel(0, 0, true);
+        load_words(&mut cpu, &[0x6000, 0xF029, 0xD005]);
+        for _ in 0..3 {
+            cpu.cycle().unwrap();
+        }
+        assert_eq!(cpu.reg[0xf], 1);
+        assert!(!cpu.pixel(0, 0));
+    }
+
+    #[test]
+    fn dirty_test() {
+        let mut cpu = Cpu::new();
+        load_words(&mut cpu, &[0x00E0, 0x6000, 0xD001]);
+        cpu.cycle().unwrap();
+        assert!(cpu.take_dirty());
+        assert!(!cpu.take_dirty());
+
+        cpu.cycle().unwrap();
+        assert!(!cpu.take_dirty());
+        cpu.cycle().unwrap();
+        assert!(cpu.take_dirty());
+    }
+
+    #[test]
+    fn press_hex_test() {
+        let mut cpu = Cpu::new();
+        for c in ['0', '9', 'a', 'F'] {
+            cpu.press_hex(c);
+        }
+        let mut expected = [false; 16];
+        for key in [0x0, 0x9, 0xa, 0xf] {
+            expected[key] = true;
+        }
+        assert_eq!(cpu.keys, expected);
+
+        // Invalid characters are ignored
+        for c in ['g', 'x', ' ', '-'] {
+            cpu.press_hex(c);
+        }
+        assert_eq!(cpu.keys, expected);
+    }
+
+    #[test]
+    fn set_keys_test() {
+        let mut cpu = Cpu::new();
+        let mut state = [false; 16];
+        state[3] = true;
+        state[0xc] = true;
+        cpu.set_keys(state);
+        assert_eq!(cpu.keys, state);
+
+        cpu.release_key(3);
+        cpu.press_key(0x10);
+        state[3] = false;
+        assert_eq!(cpu.keys, state);
+    }
+
+    #[test]
+    fn skp_test() {
+        let mut cpu = Cpu::new();
+        load_words(&mut cpu, &[0xE09E, 0x0000, 0xE0A1, 0xE09E]);
+        cpu.reg[0] = 0x5;
+        cpu.press_key(0x5);
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.pc, 0x204);
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.pc, 0x206);
+
+        cpu.release_key(0x5);
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.pc, 0x208);
+    }
+
+    #[test]
+    fn ld_reg_k_test() {
+        let mut cpu = Cpu::new();
+        load_words(&mut cpu, &[0xF30A]);
+        cpu.cycle().unwrap();
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.pc, 0x200);
+
+        cpu.press_key(0xb);
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.pc, 0x202);
+        assert_eq!(cpu.reg[3], 0xb);
+    }
+
+    #[test]
+    fn ld_reg_k_timers_test() {
+        let mut cpu = Cpu::new();
+        // Set both timers to 20, then wait for a key
+        load_words(&mut cpu, &[0x6014, 0xF015, 0xF018, 0xF30A, 0x1208]);
+        cpu.run_frame(10).unwrap();
+        assert_eq!(cpu.pc, 0x206);
+        assert_eq!((cpu.delay_timer(), cpu.sound_timer()), (19, 19));
+
+        // The timers keep counting down while the CPU is blocked
+        for _ in 0..5 {
+            cpu.run_frame(10).unwrap();
+        }
+        assert_eq!(cpu.pc, 0x206);
+        assert_eq!((cpu.delay_timer(), cpu.sound_timer()), (14, 14));
+
+        cpu.press_key(0x2);
+        cpu.run_frame(10).unwrap();
+        assert_eq!(cpu.pc, 0x208);
+        assert_eq!(cpu.reg[3], 0x2);
+        assert_eq!(cpu.delay_timer(), 13);
+    }
+
+    #[test]
+    fn key_event_test() {
+        let mut cpu = Cpu::new();
+        load_words(&mut cpu, &[0xF30A, 0x1202]);
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.pc, 0x200);
+
+        // A press and release between two frames still reaches the waiting instruction
+        cpu.push_key_event(KeyEvent::Pressed(0x7));
+        cpu.push_key_event(KeyEvent::Released(0x7));
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.pc, 0x202);
+        assert_eq!(cpu.reg[3], 0x7);
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.first_pressed_key(), None);
+    }
+
+    #[test]
+    fn first_pressed_key_test() {
+        let mut cpu = Cpu::new();
+        assert_eq!(cpu.first_pressed_key(), None);
+        cpu.press_key(0xa);
+        cpu.press_key(0x3);
+        assert_eq!(cpu.first_pressed_key(), Some(0x3));
+        cpu.release_key(0x3);
+        assert_eq!(cpu.first_pressed_key(), Some(0xa));
+    }
+
+    #[test]
+    fn memory_slice_test() {
+        let cpu = Cpu::new();
+        assert_eq!(cpu.memory_slice(FONT_BASE_ADDRESS, FONT.len()), FONT);
+        assert_eq!(cpu.memory_slice(0xffe, 10).len(), 2);
+        assert!(cpu.memory_slice(0x1000, 10).is_empty());
+        assert_eq!(cpu.memory_slice(0x200, usize::MAX).len(), 0xe00);
+    }
+
+    #[test]
+    fn poke_test() {
+        let mut cpu = Cpu::new();
+        cpu.poke(0x300, 0xab);
+        assert_eq!(cpu.memory_slice(0x300, 1), [0xab]);
+
+        cpu.poke(0x1301, 0xcd);
+        assert_eq!(cpu.memory_slice(0x301, 1), [0xcd]);
+    }
+
+    #[test]
+    fn odd_length_rom_test() {
+        let mut cpu = Cpu::new();
+        cpu.load_rom(&[0xff; 4]).unwrap();
+        cpu.load_rom(&[0x60, 0x05, 0x70]).unwrap();
+        assert_eq!(cpu.memory[0x200..0x204], [0x60, 0x05, 0x70, 0x00]);
+
+        cpu.cycle().unwrap();
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.pc, 0x204);
+        assert_eq!(cpu.reg[0], 0x05);
+    }
+
+    #[test]
+    fn odd_pc_test() {
+        let mut cpu = Cpu::new();
+        cpu.load_rom(&[0x12, 0x03, 0x00, 0x60, 0x2A]).unwrap();
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.pc, 0x203);
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.pc, 0x205);
+        assert_eq!(cpu.reg[0], 0x2a);
+    }
+
+    #[test]
+    fn add_imm_leaves_vf_test() {
+        let mut cpu = Cpu::new();
+        load_words(&mut cpu, &[0x7302, 0x7302]);
+        cpu.reg[3] = 0xff;
+        cpu.reg[0xf] = 0x00;
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.reg[3], 0x01);
+        assert_eq!(cpu.reg[0xf], 0x00);
+
+        cpu.reg[3] = 0xff;
+        cpu.reg[0xf] = 0x42;
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.reg[3], 0x01);
+        assert_eq!(cpu.reg[0xf], 0x42);
+    }
+
+    #[test]
+    fn add_imm_vf_target_test() {
+        let mut cpu = Cpu::new();
+        load_words(&mut cpu, &[0x7F02]);
+        cpu.reg[0xf] = 0xff;
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.reg[0xf], 0x01);
+    }
+
+    #[test]
+    fn invalid_opcode_test() {
+        let mut cpu = Cpu::new();
+        load_words(&mut cpu, &[0x6001, 0xFFFF]);
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.cycle(), Err(ExecError::InvalidOpcode { opcode: 0xFFFF, pc: 0x202 }));
+        assert_eq!(cpu.pc, 0x202);
+    }
+
+    #[cfg(feature = "logging")]
+    #[test]
+    fn invalid_opcode_log_test() {
+        use std::sync::Mutex;
+
+        /// Records warnings. Tests run in parallel, so other tests' warnings may appear too.
+        struct TestLogger(Mutex<Vec<String>>);
+
+        impl log::Log for TestLogger {
+            fn enabled(&self, metadata: &log::Metadata) -> bool {
+                metadata.level() <= log::Level::Warn
+            }
+
+            fn log(&self, record: &log::Record) {
+                if self.enabled(record.metadata()) {
+                    self.0.lock().unwrap().push(record.args().to_string());
+                }
+            }
+
+            fn flush(&self) {}
+        }
+
+        static LOGGER: TestLogger = TestLogger(Mutex::new(Vec::new()));
+        log::set_logger(&LOGGER).unwrap();
+        log::set_max_level(log::LevelFilter::Warn);
+
+        let mut cpu = Cpu::new();
+        load_words(&mut cpu, &[0xE3FF]);
+        assert!(cpu.cycle().is_err());
+        let warnings = LOGGER.0.lock().unwrap();
+        assert!(warnings.iter().any(|w| w == "invalid opcode 0xe3ff at 0x0200"));
+    }
+
+    #[test]
+    fn run_frame_timer_test() {
+        let mut cpu = Cpu::new();
+        // Set the delay timer to 30, then busy wait for it to reach zero
+        load_words(&mut cpu, &[0x601E, 0xF015, 0xF107, 0x3100, 0x1204, 0x120A]);
+        for _ in 0..10 {
+            cpu.run_frame(10).unwrap();
+        }
+        assert_eq!(cpu.delay_timer, 20);
+        assert_eq!(cpu.reg[1], 21);
+        assert_eq!(cpu.cycle_count(), 100);
+
+        for _ in 0..20 {
+            cpu.run_frame(10).unwrap();
+        }
+        assert_eq!(cpu.delay_timer, 0);
+
+        // The loop sees the timer expire in the next frame
+        cpu.run_frame(10).unwrap();
+        assert_eq!(cpu.pc, 0x20A);
+    }
+
+    #[test]
+    fn run_frame_display_wait_test() {
+        let mut cpu = Cpu::new();
+        cpu.set_quirks(Quirks { display_wait: true, ..Quirks::default() });
+        // Draw, count, and loop
+        load_words(&mut cpu, &[0xD001, 0x7201, 0x1200]);
+        cpu.run_frame(100).unwrap();
+        assert_eq!(cpu.cycle_count(), 1);
+        cpu.run_frame(100).unwrap();
+        assert_eq!(cpu.cycle_count(), 4);
+        assert_eq!(cpu.reg[2], 1);
+    }
+
+    #[test]
+    fn run_frame_events_test() {
+        let mut cpu = Cpu::new();
+        // Draw the same font row twice, then start the buzzer and loop
+        load_words(&mut cpu, &[0xA100, 0xD001, 0xD001, 0x6005, 0xF018, 0x120A]);
+        let events = cpu.run_frame(2).unwrap();
+        assert_eq!(events, FrameEvents { draw_occurred: true, ..FrameEvents::default() });
+        let events = cpu.run_frame(1).unwrap();
+        assert!(events.collision_occurred && events.draw_occurred && !events.beep_started);
+        let events = cpu.run_frame(10).unwrap();
+        assert_eq!(events, FrameEvents { beep_started: true, ..FrameEvents::default() });
+        // Still beeping, so it didn't start this frame
+        assert_eq!(cpu.run_frame(10).unwrap(), FrameEvents::default());
+    }
+
+    #[test]
+    fn is_halted_test() {
+        let mut cpu = Cpu::new();
+        load_words(&mut cpu, &[0x6001, 0x1204, 0x1204]);
+        assert!(!cpu.is_halted());
+        cpu.cycle().unwrap();
+        // A jump to somewhere else isn't a halt
+        assert!(!cpu.is_halted());
+        cpu.cycle().unwrap();
+        assert!(cpu.is_halted());
+        cpu.cycle().unwrap();
+        assert!(cpu.is_halted());
+        assert_eq!(cpu.pc, 0x204);
+    }
+
+    #[test]
+    fn ld_i_long_test() {
+        let mut cpu = Cpu::new();
+        load_words(&mut cpu, &[0xF000, 0x1234, 0x6001]);
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.index, 0x1234);
+        assert_eq!(cpu.pc, 0x204);
+        assert_eq!(cpu.cycle_count(), 1);
+    }
+
+    #[test]
+    fn skip_ld_i_long_test() {
+        let mut cpu = Cpu::new();
+        // The skip steps over both words of the long load
+        load_words(&mut cpu, &[0x3000, 0xF000, 0x1234, 0x6001]);
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.pc, 0x206);
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.reg[0], 1);
+        assert_eq!(cpu.index, 0);
+    }
+
+    #[test]
+    fn plane_test() {
+        let mut cpu = Cpu::new();
+        // Select plane 1 (mask 2) and draw the font sprite for 0
+        load_words(&mut cpu, &[0xF201, 0xF029, 0xD015]);
+        for _ in 0..3 {
+            cpu.cycle().unwrap();
+        }
+        assert!(cpu.plane_framebuffer(0).iter().all(|&pixel| !pixel));
+        assert!(cpu.plane_framebuffer(1)[0]);
+        assert_eq!(cpu.plane_framebuffer(1).iter().filter(|&&pixel| pixel).count(), 14);
+    }
+
+    #[test]
+    fn plane_both_test() {
+        let mut cpu = Cpu::new();
+        // With both planes selected DRW reads a sprite per plane, and CLS clears only the
+        // selected planes
+        load_words(&mut cpu, &[0xF301, 0xA20C, 0xD011, 0xF101, 0x00E0, 0x0000, 0x80C0]);
+        for _ in 0..3 {
+            cpu.cycle().unwrap();
+        }
+        assert_eq!(cpu.plane_framebuffer(0)[..2], [true, false]);
+        assert_eq!(cpu.plane_framebuffer(1)[..2], [true, true]);
+        cpu.cycle().unwrap();
+        cpu.cycle().unwrap();
+        assert!(cpu.plane_framebuffer(0).iter().all(|&pixel| !pixel));
+        assert_eq!(cpu.plane_framebuffer(1)[..2], [true, true]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn advance_test() {
+        use std::time::Duration;
+
+        let mut cpu = Cpu::new();
+        load_words(&mut cpu, &[0x1200]);
+        cpu.delay_timer = 100;
+        cpu.advance(Duration::from_millis(100)).unwrap();
+        assert_eq!(cpu.cycle_count(), 70);
+        assert_eq!(cpu.delay_timer, 94);
+
+        // Partial cycles and ticks carry over between calls
+        for _ in 0..10 {
+            cpu.advance(Duration::from_micros(1_000)).unwrap();
+        }
+        assert_eq!(cpu.cycle_count(), 77);
+        assert_eq!(cpu.delay_timer, 94);
+        cpu.advance(Duration::from_micros(6_700)).unwrap();
+        assert_eq!(cpu.delay_timer, 93);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn clock_hz_test() {
+        use std::time::Duration;
+
+        let mut cpu = Cpu::new();
+        load_words(&mut cpu, &[0x1200]);
+        assert_eq!(cpu.clock_hz(), 700);
+        cpu.set_clock_hz(1000);
+        assert_eq!(cpu.clock_hz(), 1000);
+        cpu.delay_timer = 100;
+        cpu.advance(Duration::from_millis(100)).unwrap();
+        assert_eq!(cpu.cycle_count(), 100);
+        // The timers don't depend on the clock
+        assert_eq!(cpu.delay_timer, 94);
+    }
+
+    #[test]
+    fn cost_test() {
+        use Instruction::*;
+        assert!(cost(&Drw(0, 1, 5)) > cost(&LdImm(0, 5)));
+
+        let mut cpu = Cpu::new();
+        load_words(&mut cpu, &[0x6005, 0xD015, 0x7001]);
+        for _ in 0..3 {
+            cpu.cycle().unwrap();
+        }
+        assert_eq!(cpu.cycle_count(), 3);
+        assert_eq!(cpu.elapsed_machine_cycles(), 1 + 8 + 1);
+        cpu.reset_cycle_count();
+        assert_eq!(cpu.elapsed_machine_cycles(), 0);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn advance_cycle_costs_test() {
+        use std::time::Duration;
+
+        let mut cpu = Cpu::new();
+        // Draw in a loop: each iteration costs 8 + 1 machine cycles
+        load_words(&mut cpu, &[0xD015, 0x1200]);
+        cpu.set_clock_hz(900);
+        cpu.set_cycle_costs_enabled(true);
+        cpu.advance(Duration::from_millis(100)).unwrap();
+        assert_eq!(cpu.elapsed_machine_cycles(), 90);
+        assert_eq!(cpu.cycle_count(), 20);
+
+        // Without costs, the same time runs one instruction per cycle
+        cpu.set_cycle_costs_enabled(false);
+        cpu.reset_cycle_count();
+        cpu.advance(Duration::from_millis(100)).unwrap();
+        assert_eq!(cpu.cycle_count(), 90);
+    }
+
+    #[test]
+    fn opcode_histogram_test() {
+        use Instruction::*;
+
+        let mut cpu = Cpu::new();
+        // Count down V0 from 3, then halt
+        load_words(&mut cpu, &[0x6003, 0x70FF, 0x3000, 0x1202, 0x1208]);
+        cpu.cycle().unwrap();
+        assert!(cpu.opcode_histogram().iter().all(|&count| count == 0));
+
+        cpu.set_histogram_enabled(true);
+        while !cpu.is_halted() {
+            cpu.cycle().unwrap();
+        }
+        let histogram = cpu.opcode_histogram();
+        assert_eq!(histogram[AddImm(0, 0).kind()], 3);
+        assert_eq!(histogram[SeImm(0, 0).kind()], 3);
+        assert_eq!(histogram[JpImm(0).kind()], 2);
+        assert_eq!(histogram.iter().sum::<u64>(), 8);
+
+        cpu.reset_histogram();
+        assert!(cpu.opcode_histogram().iter().all(|&count| count == 0));
+    }
+
+    #[test]
+    fn ret_empty_stack_test() {
+        let mut cpu = Cpu::new();
+        load_words(&mut cpu, &[0x00EE]);
+        assert_eq!(cpu.cycle(), Err(ExecError::StackUnderflow { pc: 0x200 }));
+        assert_eq!(cpu.pc, 0x200);
+    }
+
+    #[test]
+    fn call_ret_test() {
+        let mut cpu = Cpu::new();
+        load_words(&mut cpu, &[0x2204, 0x1202, 0x00EE]);
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.pc, 0x204);
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.pc, 0x202);
+        assert_eq!(cpu.stack.sp, 0);
+    }
+
+    #[test]
+    fn nested_calls_test() {
+        let mut cpu = Cpu::new();
+        // Call a chain of 16 subroutines at 0x300, 0x304, ..., each calling the next, then halt
+        load_words(&mut cpu, &[0x2300, 0x1202]);
+        for i in 0..16 {
+            let addr = 0x300 + 4 * i;
+            let call = 0x2000 | (addr + 4) as u16;
+            let sub = if i < 15 { [call, 0x00EE] } else { [0x00EE, 0x0000] };
+            for (j, word) in sub.iter().enumerate() {
+                cpu.memory[addr + 2 * j..addr + 2 * j + 2].copy_from_slice(&word.to_be_bytes());
+            }
+        }
+
+        let mut depth = 0;
+        while !cpu.is_halted() {
+            cpu.cycle().unwrap();
+            depth = depth.max(cpu.call_stack().len());
+        }
+        assert_eq!(depth, 16);
+        assert!(cpu.call_stack().is_empty());
+        assert_eq!(cpu.pc, 0x202);
+    }
+
+    #[test]
+    fn stack_overflow_test() {
+        let mut cpu = Cpu::new();
+        // Recurse forever
+        load_words(&mut cpu, &[0x2200]);
+        for _ in 0..16 {
+            cpu.cycle().unwrap();
+        }
+        assert_eq!(cpu.call_stack(), &[0x202; 16]);
+
+        // The 17th call fails instead of wrapping, leaving PC and the stack as they were
+        assert_eq!(cpu.cycle(), Err(ExecError::StackOverflow { pc: 0x200, depth: 16 }));
+        assert_eq!(cpu.pc, 0x200);
+        assert_eq!(cpu.call_stack(), &[0x202; 16]);
+    }
+
+    #[test]
+    fn call_boundary_test() {
+        let mut cpu = Cpu::new();
+        // A call in the last word of memory returns to address 0, and a nested call returns to
+        // its caller as usual
+        cpu.memory[0xffe..].copy_from_slice(&[0x23, 0x00]);
+        cpu.memory[0x300..0x306].copy_from_slice(&[0x23, 0x04, 0x00, 0xEE, 0x00, 0xEE]);
+        cpu.pc = 0xffe;
+        cpu.cycle().unwrap();
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.call_stack(), &[0x000, 0x302]);
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.pc, 0x302);
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.pc, 0x000);
+
+        // A PC past the end of memory pushes a wrapped return address
+        cpu.pc = 0x1204;
+        cpu.execute(Instruction::Call(0x300)).unwrap();
+        assert_eq!(cpu.call_stack(), &[0x204]);
+    }
+
+    #[test]
+    fn strict_test() {
+        let mut cpu = Cpu::new();
+        cpu.set_strict(true);
+        cpu.load_rom(&[0x60, 0x01, 0x13, 0x00]).unwrap();
+        cpu.cycle().unwrap();
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.pc, 0x300);
+        assert_eq!(cpu.cycle(), Err(ExecError::UninitializedRead { addr: 0x300 }));
+
+        // Memory written by the program counts as initialized
+        cpu.poke(0x300, 0x12);
+        assert_eq!(cpu.cycle(), Err(ExecError::UninitializedRead { addr: 0x301 }));
+        cpu.poke(0x301, 0x00);
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.pc, 0x200);
+
+        // The operand of LD I, LONG must be written too, wrapping at the end of memory
+        cpu.poke(0xffe, 0xF0);
+        cpu.poke(0xfff, 0x00);
+        cpu.pc = 0xffe;
+        assert_eq!(cpu.cycle(), Err(ExecError::UninitializedRead { addr: 0x000 }));
+        cpu.poke(0x000, 0x03);
+        assert_eq!(cpu.cycle(), Err(ExecError::UninitializedRead { addr: 0x001 }));
+        cpu.poke(0x001, 0x00);
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.index, 0x300);
+
+        // Blank memory is just executed outside strict mode
+        cpu.set_strict(false);
+        cpu.pc = 0x400;
+        assert!(!matches!(cpu.cycle(), Err(ExecError::UninitializedRead { .. })));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn load_rom_file_test() {
+        let path = std::env::temp_dir().join(format!("chip8-load-rom-file-{}.ch8", std::process::id()));
+        std::fs::write(&path, [0x12, 0x00]).unwrap();
+        let mut cpu = Cpu::new();
+        let result = cpu.load_rom_file(&path);
+        std::fs::remove_file(&path).unwrap();
+        result.unwrap();
+        assert_eq!(cpu.memory[0x200..0x202], [0x12, 0x00]);
+
+        let result = cpu.load_rom_file(&path);
+        assert!(matches!(result, Err(LoadError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound));
+    }
+
+    #[test]
+    fn large_memory_test() {
+        let mut cpu = Cpu::<65536>::new_sized();
+        // Load I with an address past 4 KB and read V0 from it
+        let mut rom = vec![0; 0x3000];
+        rom[..6].copy_from_slice(&[0xF0, 0x00, 0x30, 0x00, 0xF0, 0x65]);
+        rom[0x3000 - 0x200] = 0x42;
+        cpu.load_rom(&rom).unwrap();
+        cpu.cycle().unwrap();
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.index, 0x3000);
+        assert_eq!(cpu.reg[0], 0x42);
+
+        // PC wraps at the end of the larger memory
+        cpu.poke(0xfffe, 0x60);
+        cpu.poke(0xffff, 0x01);
+        cpu.pc = 0xfffe;
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.pc, 0);
+
+        let mut cpu = Cpu::<65536>::new_sized();
+        let rom = vec![0; 0x10000];
+        assert_eq!(cpu.load_rom(&rom), Err(LoadError::RomTooLarge { size: 0x10000, max: 0xfe00 }));
+    }
+
+    #[test]
+    fn sys_test() {
+        let mut cpu = Cpu::new();
+        load_words(&mut cpu, &[0x0123]);
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.pc, 0x202);
+    }
+
+    #[test]
+    fn clone_test() {
+        let mut cpu = Cpu::new();
+        load_words(&mut cpu, &[0x6005, 0x7001]);
+        cpu.cycle().unwrap();
+
+        let mut clone = cpu.clone();
+        clone.cycle().unwrap();
+        assert_eq!(clone.reg[0], 6);
+        assert_eq!(clone.pc, 0x204);
+        assert_eq!(cpu.reg[0], 5);
+        assert_eq!(cpu.pc, 0x202);
+        assert_eq!(cpu.cycle_count(), 1);
+    }
+
+    #[test]
+    fn debug_test() {
+        let cpu = Cpu::new();
+        let debug = format!("{cpu:?}");
+        assert!(debug.starts_with("Cpu { pc: 0x0200, index: 0x0000, reg: [0, 0,"));
+        assert!(debug.contains("memory: [4096 bytes]"));
+        assert!(debug.contains("display_size: (64, 32)"));
+        assert!(debug.contains("draw_occurred: false, collision_occurred: false"));
+    }
+
+    #[test]
+    fn eq_test() {
+        use crate::builder::CpuBuilder;
+
+        // Clear the screen, call a subroutine that draws a digit, then halt
+        let program = [0x00E0, 0x2206, 0x1204, 0x6107, 0xF129, 0xD015, 0x00EE];
+        let mut a = Cpu::new();
+        let mut b = CpuBuilder::new().seed(1234).build().unwrap();
+        load_words(&mut a, &program);
+        load_words(&mut b, &program);
+        assert_eq!(a, b);
+
+        for _ in 0..4 {
+            a.cycle().unwrap();
+        }
+        assert_ne!(a, b);
+        a.cycle().unwrap();
+        a.cycle().unwrap();
+        for _ in 0..8 {
+            b.cycle().unwrap();
+        }
+        // a halted after 6 cycles, b after 8, but the state is the same
+        assert_eq!(a, b);
+
+        b.press_key(1);
+        assert_ne!(a, b);
+        b.release_key(1);
+        assert_eq!(a, b);
+
+        // A collision on the last draw makes a difference
+        b.collision_occurred = true;
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn load_sprite_test() {
+        let mut cpu = Cpu::new();
+        cpu.load_sprite(0x300, &[0b1000_0001, 0b0100_0010, 0b0011_1100]);
+        cpu.set_index(0x300);
+        load_words(&mut cpu, &[0xD013]);
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.render_ascii().lines().take(3).map(|row| &row[..8]).collect::<Vec<_>>(), [
+            "#......#",
+            ".#....#.",
+            "..####..",
+        ]);
+    }
+
+    #[test]
+    fn subn_vf_operand_test() {
+        // VF as the subtrahend: V1 = VF - V1
+        let mut cpu = Cpu::new();
+        load_words(&mut cpu, &[0x81F7]);
+        cpu.reg[1] = 3;
+        cpu.reg[0xf] = 10;
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.reg[1], 7);
+        assert_eq!(cpu.reg[0xf], 1);
+
+        let mut cpu = Cpu::new();
+        load_words(&mut cpu, &[0x81F7]);
+        cpu.reg[1] = 10;
+        cpu.reg[0xf] = 3;
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.reg[1], 249);
+        assert_eq!(cpu.reg[0xf], 0);
+
+        // VF as the destination: the flag overwrites the result
+        let mut cpu = Cpu::new();
+        load_words(&mut cpu, &[0x8F17]);
+        cpu.reg[1] = 10;
+        cpu.reg[0xf] = 3;
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.reg[0xf], 1);
+    }
+
+    #[test]
+    fn drw_count_clipped_rows_quirk_test() {
+        let mut cpu = Cpu::new();
+        cpu.set_quirks(Quirks { drw_count_clipped_rows: true, ..Quirks::default() });
+        // Draw a 5-row digit 2 rows above the bottom edge twice in high resolution
+        load_words(&mut cpu, &[0x00FF, 0x603E, 0xF029, 0xD005, 0xD005, 0x00FE, 0xD005]);
+        for _ in 0..4 {
+            cpu.cycle().unwrap();
+        }
+        // No collisions, 3 rows clipped
+        assert_eq!(cpu.reg[0xf], 3);
+        cpu.cycle().unwrap();
+        // Both visible rows collide
+        assert_eq!(cpu.reg[0xf], 5);
+
+        // Low resolution only reports whether there was a collision
+        cpu.cycle().unwrap();
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.reg[0xf], 0);
+    }
+
+    #[test]
+    fn drw_clipped_rows_default_test() {
+        let mut cpu = Cpu::new();
+        load_words(&mut cpu, &[0x00FF, 0x603E, 0xF029, 0xD005, 0xD005]);
+        for _ in 0..4 {
+            cpu.cycle().unwrap();
+        }
+        assert_eq!(cpu.reg[0xf], 0);
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.reg[0xf], 1);
+    }
+
+    #[test]
+    fn trace_line_test() {
+        let mut cpu = Cpu::new();
+        load_words(&mut cpu, &[0x6A02, 0xFFFF, 0xD125, 0xF000, 0x1234]);
+        assert_eq!(cpu.trace_line(0x200), "0x0200: 6A02  LD VA, 0x02");
+        assert_eq!(cpu.trace_line(0x202), "0x0202: FFFF  ???");
+        assert_eq!(cpu.trace_line(0x204), "0x0204: D125  DRW V1, V2, 5");
+        assert_eq!(cpu.trace_line(0x206), "0x0206: F000  LD I, LONG 0x1234");
+    }
+
+    #[test]
+    fn peek_skip_test() {
+        let mut cpu = Cpu::new();
+        load_words(&mut cpu, &[0x3005, 0x6005, 0x3005, 0xE19E]);
+        // SE V0, 5 with V0 = 0 doesn't skip
+        assert_eq!(cpu.peek_skip(), Some(false));
+        cpu.cycle().unwrap();
+        // LD isn't a skip
+        assert_eq!(cpu.peek_skip(), None);
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.peek_skip(), Some(true));
+        assert_eq!(cpu.pc, 0x204);
+
+        cpu.pc = 0x206;
+        assert_eq!(cpu.peek_skip(), Some(false));
+        cpu.press_key(0);
+        assert_eq!(cpu.peek_skip(), Some(true));
+    }
+
+    #[test]
+    fn fixed_rng_test() {
+        use crate::rng::FixedRng;
+
+        let mut cpu = Cpu::new();
+        cpu.set_rng(Box::new(FixedRng::new(vec![0xAB, 0xFF, 0x12])));
+        load_words(&mut cpu, &[0xC0FF, 0xC10F, 0xC2F0]);
+        for _ in 0..3 {
+            cpu.cycle().unwrap();
+        }
+        assert_eq!(cpu.reg[..3], [0xAB, 0x0F, 0x10]);
+
+        // A clone continues the same sequence
+        let mut clone = cpu.clone();
+        cpu.pc = 0x200;
+        clone.pc = 0x200;
+        cpu.cycle().unwrap();
+        clone.cycle().unwrap();
+        assert_eq!(cpu.reg[0], clone.reg[0]);
+    }
+
+    #[test]
+    fn set_font_test() {
+        let mut cpu = Cpu::new();
+        let font: Vec<u8> = (0..80).collect();
+        cpu.set_font(0x050, &font).unwrap();
+        load_words(&mut cpu, &[0x600A, 0xF029, 0x6103, 0xF130]);
+        cpu.cycle().unwrap();
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.index, 0x050 + 10 * 5);
+        let i = cpu.index as usize;
+        assert_eq!(cpu.memory[i..i + 5], [50, 51, 52, 53, 54]);
+
+        // The big font stays put
+        cpu.cycle().unwrap();
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.index, FONT_BASE_ADDRESS + 80 + 3 * 10);
+    }
+
+    #[test]
+    fn set_font_invalid_test() {
+        let mut cpu = Cpu::new();
+        assert_eq!(cpu.set_font(0x050, &[0; 79]), Err(LoadError::InvalidFontSize { size: 79, expected: 80 }));
+        assert_eq!(cpu.set_font(0xFB1, &[0; 80]), Err(LoadError::FontOutOfMemory { font_base: 0xFB1 }));
+        assert_eq!(cpu.font_base, FONT_BASE_ADDRESS);
+        assert!(cpu.set_font(0xFB0, &[0; 80]).is_ok());
+        assert_eq!(cpu.font_base, 0xFB0);
+    }
+
+    #[test]
+    fn from_memory_test() {
+        let mut memory = [0; 4096];
+        memory[0x200..0x204].copy_from_slice(&[0x6A, 0x42, 0xAF, 0xFF]);
+        memory[0x100] = 0x99;
+        let mut cpu = Cpu::from_memory(memory);
+        assert_eq!(cpu.pc, 0x200);
+        // The image is used as is, without the font written over it
+        assert_eq!(cpu.memory[0x100], 0x99);
+        cpu.cycle().unwrap();
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.reg[0xa], 0x42);
+        assert_eq!(cpu.index, 0xfff);
+    }
+
+    #[test]
+    fn registers_test() {
+        let mut cpu = Cpu::new();
+        let regs = core::array::from_fn(|i| i as u8 * 3);
+        cpu.set_registers(regs);
+        assert_eq!(cpu.registers(), &regs);
+        assert_eq!(cpu.registers()[0xf], 45);
+
+        // VF is a real register that instructions write to
+        load_words(&mut cpu, &[0x8FF4]);
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.registers()[0xf], 0);
+    }
+
+    #[test]
+    fn run_until_halt_test() {
+        let mut cpu = Cpu::new();
+        load_words(&mut cpu, &[0x6001, 0x7001, 0x3005, 0x1202, 0x1208]);
+        // V0 counts up from 1 to 5, three instructions per loop, then the skip lands on the halt
+        cpu.run_until_halt(100).unwrap();
+        assert_eq!(cpu.reg[0], 5);
+        assert_eq!(cpu.pc, 0x208);
+        assert_eq!(cpu.cycle_count(), 1 + 3 * 3 + 2);
+
+        // Already halted, so no cycles are needed
+        cpu.run_until_halt(0).unwrap();
+        assert_eq!(cpu.cycle_count(), 12);
+    }
+
+    #[test]
+    fn run_until_halt_limit_test() {
+        let mut cpu = Cpu::new();
+        load_words(&mut cpu, &[0x7001, 0x1200]);
+        assert_eq!(cpu.run_until_halt(1000), Err(ExecError::CycleLimit { cycles: 1000 }));
+        assert_eq!(cpu.cycle_count(), 1000);
+    }
+
+    #[test]
+    fn audio_samples_test() {
+        let mut cpu = Cpu::new();
+        assert!(cpu.audio_samples(44100, 512).iter().all(|&sample| sample == 0.0));
+
+        cpu.sound_timer = 2;
+        let samples = cpu.audio_samples(44100, 512);
+        assert_eq!(samples.len(), 512);
+        assert!(samples.iter().all(|&sample| sample != 0.0));
+        // 440 Hz at 44.1 kHz is about 100 samples per period
+        assert!(samples[..50].iter().all(|&sample| sample > 0.0));
+        assert!(samples[51..100].iter().all(|&sample| sample < 0.0));
+
+        cpu.tick_timers();
+        cpu.tick_timers();
+        assert!(cpu.audio_samples(44100, 512).iter().all(|&sample| sample == 0.0));
+    }
+
+    #[test]
+    fn audio_pattern_test() {
+        let mut cpu = Cpu::new();
+        assert_eq!(cpu.audio_pattern(), &[0; 16]);
+        assert_eq!(cpu.audio_pitch(), 64);
+
+        let pattern = core::array::from_fn(|i| i as u8 * 0x11);
+        cpu.memory[0x300..0x310].copy_from_slice(&pattern);
+        cpu.reg[2] = 112;
+        load_words(&mut cpu, &[0xA300, 0xF002, 0xF23A]);
+        for _ in 0..3 {
+            cpu.cycle().unwrap();
+        }
+        assert_eq!(cpu.audio_pattern(), &pattern);
+        assert_eq!(cpu.audio_pitch(), 112);
+
+        cpu.reset();
+        assert_eq!(cpu.audio_pattern(), &[0; 16]);
+        assert_eq!(cpu.audio_pitch(), 64);
+    }
+
+    #[test]
+    fn diff_test() {
+        let mut cpu = Cpu::new();
+        load_words(&mut cpu, &[0x6342, 0x2300]);
+        let before = cpu.snapshot();
+        cpu.cycle().unwrap();
+        let after = cpu.snapshot();
+        assert_eq!(before.diff(&after), [
+            StateChange::Pc { old: 0x200, new: 0x202 },
+            StateChange::Register { reg: 3, old: 0, new: 0x42 },
+        ]);
+        assert!(after.diff(&after).is_empty());
+
+        cpu.cycle().unwrap();
+        assert_eq!(after.diff(&cpu.snapshot()), [
+            StateChange::Pc { old: 0x202, new: 0x300 },
+            StateChange::StackPointer { old: 0, new: 1 },
+            StateChange::StackEntry { slot: 0, old: 0, new: 0x204 },
+        ]);
+    }
+
+    #[test]
+    fn drw_large_sprite_test() {
+        let mut cpu = Cpu::new();
+        cpu.index = 0x300;
+        cpu.memory[0x300..0x320].fill(0xff);
+        cpu.reg[0] = 10;
+        cpu.reg[1] = 20;
+        load_words(&mut cpu, &[0xD010, 0x00FF, 0xD010, 0xD010]);
+
+        // Nothing is drawn in low resolution
+        cpu.cycle().unwrap();
+        assert!(cpu.framebuffer().iter().all(|&pixel| !pixel));
+
+        cpu.cycle().unwrap();
+        cpu.cycle().unwrap();
+        let (width, _) = cpu.display_size();
+        let lit: Vec<(usize, usize)> = cpu.framebuffer().iter().enumerate()
+            .filter(|(_, &pixel)| pixel)
+            .map(|(i, _)| (i % width, i / width))
+            .collect();
+        assert_eq!(lit.len(), 16 * 16);
+        assert_eq!(lit.first(), Some(&(10, 20)));
+        assert_eq!(lit.last(), Some(&(25, 35)));
+        assert_eq!(cpu.reg[0xf], 0);
+
+        // All 16 rows collide when drawn again
+        cpu.quirks.drw_count_clipped_rows = true;
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.reg[0xf], 16);
+        assert!(cpu.framebuffer().iter().all(|&pixel| !pixel));
+    }
+
+    #[test]
+    fn pre_cycle_hook_test() {
+        let mut cpu = Cpu::new();
+        load_words(&mut cpu, &[0x6001, 0x6102, 0x6203]);
+        cpu.set_pre_cycle_hook(Box::new(|pc| if pc == 0x202 { Control::Pause } else { Control::Continue }));
+        for _ in 0..3 {
+            cpu.cycle().unwrap();
+        }
+        // Paused at 0x202 without executing it
+        assert_eq!(cpu.pc, 0x202);
+        assert_eq!(cpu.reg[..3], [1, 0, 0]);
+        assert_eq!(cpu.cycle_count(), 1);
+
+        cpu.set_pre_cycle_hook(Box::new(|_| Control::Abort));
+        assert_eq!(cpu.cycle(), Err(ExecError::Aborted { pc: 0x202 }));
+        assert_eq!(cpu.pc, 0x202);
+
+        cpu.clear_pre_cycle_hook();
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.reg[1], 2);
+    }
+
+    #[test]
+    fn peek_test() {
+        let mut cpu = Cpu::new();
+        cpu.set_strict(true);
+        assert_eq!(cpu.peek(), Ok(Instruction::Sys(0)));
+
+        cpu.load_rom(&[0xD0, 0x15, 0xF0, 0x00, 0x12, 0x34, 0xFF, 0xFF]).unwrap();
+        let before = cpu.snapshot();
+        assert_eq!(cpu.peek(), Ok(Instruction::Drw(0, 1, 5)));
+        assert_eq!(cpu.snapshot(), before);
+        assert_eq!(cpu.pc, 0x200);
+
+        cpu.pc = 0x202;
+        assert_eq!(cpu.peek(), Ok(Instruction::LdILong(0x1234)));
+        cpu.pc = 0x206;
+        assert_eq!(cpu.peek(), Err(DecodeError::InvalidOpcode(0xFFFF)));
+    }
+
+    #[test]
+    fn flag_written_last_test() {
+        // With VF as the destination, the flag overwrites the result
+        for (word, vf, vy, flag) in [
+            (0x8F14, 0xff, 0x03, 1),    // ADD VF, V1: result 0x02, carry
+            (0x8F15, 0x01, 0x02, 0),    // SUB VF, V1: result 0xff, borrow
+            (0x8F17, 0x01, 0x03, 1),    // SUBN VF, V1: result 0x02, no borrow
+            (0x8FF6, 0x02, 0x00, 0),    // SHR VF: result 0x01, bit shifted out 0
+            (0x8FFE, 0x40, 0x00, 0),    // SHL VF: result 0x80, bit shifted out 0
+        ] {
+            let mut cpu = Cpu::new();
+            cpu.reg[0xf] = vf;
+            cpu.reg[1] = vy;
+            load_words(&mut cpu, &[word]);
+            cpu.cycle().unwrap();
+            assert_eq!(cpu.reg[0xf], flag, "{word:04X}");
+        }
+
+        // DRW reads VF as a coordinate before replacing it with the collision flag
+        let mut cpu = Cpu::new();
+        cpu.reg[0xf] = 3;
+        cpu.index = 0x300;
+        cpu.memory[0x300] = 0x80;
+        load_words(&mut cpu, &[0xDFF1]);
+        cpu.cycle().unwrap();
+        assert!(cpu.pixel(3, 3));
+        assert_eq!(cpu.reg[0xf], 0);
+    }
+
+    #[test]
+    fn keys_bitmask_test() {
+        let mut cpu = Cpu::new();
+        cpu.set_keys_bitmask(0x8001);
+        assert_eq!(cpu.keys_bitmask(), 0x8001);
+        assert!(cpu.keys[0x0] && cpu.keys[0xf]);
+        assert_eq!(cpu.keys.iter().filter(|&&pressed| pressed).count(), 2);
+
+        // SKP V0 and SKP V1 with V0 = 0x0 and V1 = 0xF both skip; SKP V2 with V2 = 0x1 doesn't
+        cpu.reg[1] = 0xf;
+        cpu.reg[2] = 0x1;
+        load_words(&mut cpu, &[0xE09E, 0x0000, 0xE19E, 0x0000, 0xE29E]);
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.pc, 0x204);
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.pc, 0x208);
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.pc, 0x20a);
+    }
+
+    #[test]
+    fn drw_wrap_memory_test() {
+        let mut cpu = Cpu::new();
+        cpu.index = 0x0ffe;
+        cpu.memory[0xffe..].copy_from_slice(&[0x80, 0x40]);
+        cpu.memory[..3].copy_from_slice(&[0x20, 0x10, 0x08]);
+        load_words(&mut cpu, &[0xD005]);
+        cpu.cycle().unwrap();
+        // The last three rows come from the start of memory
+        for row in 0..5 {
+            assert!(cpu.pixel(row, row));
+        }
+    }
+
+    #[test]
+    fn fetch_decode_execute_test() {
+        let mut cpu = Cpu::new();
+        load_words(&mut cpu, &[0x6A07, 0x00EE, 0xF000, 0x1234]);
+
+        let word = cpu.fetch();
+        assert_eq!(word, 0x6A07);
+        assert_eq!(cpu.pc, 0x202);
+        let instr = Chip8::decode(word).unwrap();
+        assert_eq!(instr, Instruction::LdImm(0xa, 0x07));
+        cpu.execute(instr).unwrap();
+        assert_eq!(cpu.reg[0xa], 0x07);
+        assert_eq!(cpu.cycle_count(), 1);
+
+        // A failed execution leaves PC pointing at the instruction
+        let instr = Chip8::decode(cpu.fetch()).unwrap();
+        assert_eq!(cpu.execute(instr), Err(ExecError::StackUnderflow { pc: 0x202 }));
+        assert_eq!(cpu.pc, 0x202);
+
+        // A double-width instruction's operand takes a second fetch
+        cpu.pc = 0x204;
+        let word = cpu.fetch();
+        assert_eq!(Chip8::decode(word), Err(DecodeError::InvalidOpcode(0xF000)));
+        let (instr, _) = Instruction::try_decode_long(word, cpu.fetch()).unwrap();
+        cpu.execute(instr).unwrap();
+        assert_eq!(cpu.index, 0x1234);
+        assert_eq!(cpu.pc, 0x208);
+    }
+
+    #[test]
+    fn call_stack_test() {
+        let mut cpu = Cpu::new();
+        // Call a subroutine that calls another, which returns straight away
+        load_words(&mut cpu, &[0x2204, 0x0000, 0x2208, 0x0000, 0x00EE]);
+        assert!(cpu.call_stack().is_empty());
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.call_stack(), &[0x202]);
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.call_stack(), &[0x202, 0x206]);
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.call_stack(), &[0x202]);
+    }
+
+    #[test]
+    fn memory_wrap_test() {
+        let mut cpu = Cpu::new();
+        cpu.set_quirks(Quirks { memory_increments_index: true, ..Quirks::default() });
+        cpu.set_registers(core::array::from_fn(|i| i as u8 + 1));
+        cpu.set_index(0x0ffc);
+        // LD [I], V5 writes 6 bytes, the last two wrapping around to address 0
+        load_words(&mut cpu, &[0xF555]);
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.memory[0xffc..], [1, 2, 3, 4]);
+        assert_eq!(cpu.memory[..2], [5, 6]);
+        assert_eq!(cpu.index, 0x1002);
+
+        // Reading back wraps the same way
+        cpu.reset();
+        cpu.set_index(0x0ffc);
+        load_words(&mut cpu, &[0xF565]);
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.reg[..7], [1, 2, 3, 4, 5, 6, 0]);
+
+        // As does LD B at the last byte, and the index increment at the top of its range
+        cpu.reset();
+        cpu.reg[0] = 123;
+        cpu.set_index(0x0fff);
+        load_words(&mut cpu, &[0xF033]);
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.memory[0xfff], 1);
+        assert_eq!(cpu.memory[..2], [2, 3]);
+        cpu.reset();
+        cpu.set_index(0xffff);
+        load_words(&mut cpu, &[0xF165]);
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.index, 0x0001);
+    }
+
+    #[test]
+    fn jp_reg_wrap_test() {
+        let mut cpu = Cpu::new();
+        cpu.reg[0] = 0x10;
+        load_words(&mut cpu, &[0xBFF8]);
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.pc, 0x008);
+
+        // The target is masked to 12 bits even when memory is larger
+        let mut cpu = Cpu::<65536>::new_sized();
+        cpu.reg[0] = 0x10;
+        cpu.load_rom(&[0xBF, 0xF8]).unwrap();
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.pc, 0x008);
+
+        // As are targets of instructions built by hand
+        cpu.execute(Instruction::JpImm(0x1234)).unwrap();
+        assert_eq!(cpu.pc, 0x234);
+        cpu.execute(Instruction::Call(0xf456)).unwrap();
+        assert_eq!(cpu.pc, 0x456);
+    }
+
+    #[test]
+    fn run_headless_test() {
+        let mut cpu = Cpu::new();
+        // Draw the digit 0 twice, then loop
+        load_words(&mut cpu, &[0xA100, 0xD005, 0xD005, 0x1206]);
+        cpu.run_headless(3).unwrap();
+        assert!(cpu.framebuffer().iter().all(|&pixel| !pixel));
+        assert_eq!(cpu.reg[0xf], 0);
+
+        // With pixels drawn beforehand, a headless draw collides with them but doesn't erase
+        // them
+        cpu.reset();
+        cpu.cycle().unwrap();
+        cpu.cycle().unwrap();
+        let frame = cpu.framebuffer().to_vec();
+        assert!(frame.iter().any(|&pixel| pixel));
+        cpu.run_headless(1).unwrap();
+        assert_eq!(cpu.reg[0xf], 1);
+        assert_eq!(cpu.framebuffer(), frame);
+
+        // Normal runs render again afterwards
+        cpu.reset();
+        for _ in 0..3 {
+            cpu.cycle().unwrap();
+        }
+        assert!(cpu.framebuffer().iter().all(|&pixel| !pixel));
+        assert_eq!(cpu.reg[0xf], 1);
+    }
+
+    #[test]
+    fn rewind_test() {
+        let mut cpu = Cpu::new();
+        load_words(&mut cpu, &[0x6001, 0x7001, 0xA300, 0xF055, 0x7001, 0x120A]);
+        assert!(!cpu.step_back());
+
+        cpu.enable_rewind(4);
+        let mut states = vec![cpu.snapshot()];
+        for _ in 0..5 {
+            cpu.cycle().unwrap();
+            states.push(cpu.snapshot());
+        }
+        assert_eq!(cpu.reg[0], 3);
+        assert_eq!(cpu.memory[0x300], 2);
+
+        for _ in 0..3 {
+            assert!(cpu.step_back());
+        }
+        assert_eq!(cpu.snapshot(), states[2]);
+        assert_eq!(cpu.pc, 0x204);
+        assert_eq!(cpu.memory[0x300], 0);
+
+        // Only the last 4 cycles were kept
+        assert!(cpu.step_back());
+        assert!(!cpu.step_back());
+        assert_eq!(cpu.snapshot(), states[1]);
+
+        // Stepping forward again after rewinding works as before
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.snapshot(), states[2]);
+
+        cpu.enable_rewind(0);
+        cpu.cycle().unwrap();
+        assert!(!cpu.step_back());
+    }
+
+    #[test]
+    fn rewind_strict_test() {
+        let mut cpu = Cpu::new();
+        cpu.set_strict(true);
+        cpu.enable_rewind(4);
+        cpu.load_rom(&[0x60, 0x01, 0x13, 0x00]).unwrap();
+        cpu.cycle().unwrap();
+        assert!(cpu.step_back());
+
+        // Memory that was never written is still caught after rewinding
+        cpu.cycle().unwrap();
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.cycle(), Err(ExecError::UninitializedRead { addr: 0x300 }));
+    }
+
+    #[test]
+    fn rewind_failed_cycle_test() {
+        let mut cpu = Cpu::new();
+        cpu.enable_rewind(4);
+        // RET with an empty stack fails
+        load_words(&mut cpu, &[0x6001, 0x00EE]);
+        let state = cpu.snapshot();
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.cycle(), Err(ExecError::StackUnderflow { pc: 0x202 }));
+
+        // The failed cycle left nothing to undo
+        assert!(cpu.step_back());
+        assert_eq!(cpu.snapshot(), state);
+        assert!(!cpu.step_back());
+    }
+
+    #[test]
+    fn input_script_test() {
+        let mut cpu = Cpu::new();
+        // Loop until key 5 is pressed
+        load_words(&mut cpu, &[0x6005, 0xE09E, 0x1202, 0x1206]);
+        cpu.load_input_script(vec![
+            (20, KeyEvent::Released(5)),
+            (10, KeyEvent::Pressed(5)),
+        ]);
+        for _ in 0..10 {
+            cpu.cycle().unwrap();
+        }
+        assert!(!cpu.keys[5]);
+        assert_eq!(cpu.pc, 0x204);
+
+        // The press is applied before the 11th instruction, so the SKP after it skips
+        cpu.cycle().unwrap();
+        assert!(cpu.keys[5]);
+        assert_eq!(cpu.pc, 0x202);
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.pc, 0x206);
+
+        for _ in 0..8 {
+            cpu.cycle().unwrap();
+        }
+        assert!(cpu.keys[5]);
+        cpu.cycle().unwrap();
+        assert!(!cpu.keys[5]);
+    }
+
+    #[test]
+    fn input_script_same_cycle_test() {
+        let mut cpu = Cpu::new();
+        load_words(&mut cpu, &[0x1200]);
+        cpu.load_input_script(vec![
+            (1, KeyEvent::Pressed(1)),
+            (1, KeyEvent::Pressed(2)),
+        ]);
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.keys_bitmask(), 0);
+
+        // Both events are applied before the second instruction
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.keys_bitmask(), 0b110);
+    }
+
+    #[test]
+    fn unaligned_jump_test() {
+        let mut cpu = Cpu::new();
+        // An aligned jump, then JP V0 and CALL to odd addresses
+        load_words(&mut cpu, &[0x1202, 0x6003, 0xB300]);
+        cpu.memory[0x303..0x305].copy_from_slice(&[0x24, 0x01]);
+        for _ in 0..4 {
+            cpu.cycle().unwrap();
+        }
+        // Nothing is recorded unless enabled
+        assert!(cpu.take_diagnostics().is_empty());
+
+        cpu.reset();
+        cpu.set_diagnostics_enabled(true);
+        for _ in 0..4 {
+            cpu.cycle().unwrap();
+        }
+        assert_eq!(cpu.pc, 0x401);
+        assert_eq!(cpu.take_diagnostics(), [
+            Diagnostic::UnalignedJump { pc: 0x204, target: 0x303 },
+            Diagnostic::UnalignedJump { pc: 0x303, target: 0x401 },
+        ]);
+        assert!(cpu.take_diagnostics().is_empty());
+    }
+
+    #[test]
+    fn rnd_seed_test() {
+        let mut a = Cpu::with_seed(42);
+        let mut b = Cpu::with_seed(42);
+        load_words(&mut a, &[0xC0FF, 0xC10F]);
+        load_words(&mut b, &[0xC0FF, 0xC10F]);
+        for _ in 0..2 {
+            a.cycle().unwrap();
+            b.cycle().unwrap();
+        }
+        assert_eq!(a.reg, b.reg);
+        assert_eq!(a.reg[1] & 0xf0, 0);
+    }
+
+    #[test]
+    fn render_ascii_test() {
+        let mut cpu = Cpu::new();
+        // Draw the font sprite for 0 at (0, 0)
+        load_words(&mut cpu, &[0x6000, 0xF029, 0xD005]);
+        for _ in 0..3 {
+            cpu.cycle().unwrap();
+        }
+
+        let dots = ".".repeat(60);
+        let expected_top = [
+            format!("####{dots}"),
+            format!("#..#{dots}"),
+            format!("#..#{dots}"),
+            format!("#..#{dots}"),
+            format!("####{dots}"),
+        ];
+        let ascii = cpu.render_ascii();
+        let lines: Vec<&str> = ascii.lines().collect();
+        assert_eq!(lines.len(), 32);
+        assert_eq!(lines[..5], expected_top);
+        assert!(lines[5..].iter().all(|line| *line == ".".repeat(64)));
+    }
+
+    #[test]
+    fn snapshot_restore_test() {
+        let mut cpu = Cpu::new();
+        cpu.reg[3] = 0x42;
+        cpu.memory[0x300] = 0xaa;
+        let state = cpu.snapshot();
+
+        let mut other = Cpu::new();
+        other.restore(&state);
+        assert_eq!(other.snapshot(), state);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_roundtrip_test() {
+        let mut cpu = Cpu::new();
+        cpu.pc = 0x234;
+        cpu.index = 0x456;
+        cpu.reg[0xf] = 1;
+        cpu.memory[0xfff] = 0x99;
+        cpu.stack.push(0x202);
+        let state = cpu.snapshot();
+
+        let json = serde_json::to_string(&state).unwrap();
+        let decoded: CpuState = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, state);
+    }
+}
@@ -0,0 +1,73 @@
+use crate::instruction::Instruction;
+
+/// Disassemble a program loaded at address `base`, yielding the address, raw opcode and
+/// decoded instruction (or `None` for an invalid opcode) of each instruction. A double-width
+/// `LD I, LONG` is yielded once with its first word as the opcode, and the listing continues
+/// after its operand word. A trailing odd byte is treated as the high byte of a word whose low
+/// byte is zero.
+pub fn disassemble(bytes: &[u8], base: u16) -> impl Iterator<Item = (u16, u16, Option<Instruction>)> + '_ {
+    let word_at = |offset: usize| {
+        let hi = *bytes.get(offset)?;
+        Some(u16::from_be_bytes([hi, bytes.get(offset + 1).copied().unwrap_or(0)]))
+    };
+    let mut offset = 0;
+    core::iter::from_fn(move || {
+        let opcode = word_at(offset)?;
+        let addr = base.wrapping_add(offset as u16);
+        // A double-width instruction cut short by the end of the program is invalid
+        let decoded = match word_at(offset + 2) {
+            Some(operand) => Instruction::try_decode_long(opcode, operand).ok(),
+            None => Instruction::try_decode(opcode).ok().map(|instr| (instr, 2)),
+        };
+        offset += decoded.as_ref().map_or(2, |&(_, size)| size as usize);
+        Some((addr, opcode, decoded.map(|(instr, _)| instr)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn disassemble_test() {
+        use Instruction::*;
+        let bytes = [0x60, 0x05, 0xA2, 0x0A, 0xFF, 0xFF, 0xD0, 0x15, 0x12];
+        let listing: Vec<_> = disassemble(&bytes, 0x200).collect();
+        assert_eq!(listing, [
+            (0x200, 0x6005, Some(LdImm(0, 0x05))),
+            (0x202, 0xA20A, Some(LdI(0x20a))),
+            (0x204, 0xFFFF, None),
+            (0x206, 0xD015, Some(Drw(0, 1, 5))),
+            (0x208, 0x1200, Some(JpImm(0x200))),
+        ]);
+    }
+
+    #[test]
+    fn disassemble_long_test() {
+        use Instruction::*;
+        let bytes = [0xF0, 0x00, 0x12, 0x34, 0x12, 0x00, 0xF0, 0x00];
+        let listing: Vec<_> = disassemble(&bytes, 0x200).collect();
+        assert_eq!(listing, [
+            (0x200, 0xF000, Some(LdILong(0x1234))),
+            (0x204, 0x1200, Some(JpImm(0x200))),
+            // Missing its operand word
+            (0x206, 0xF000, None),
+        ]);
+    }
+
+    #[test]
+    fn listing_test() {
+        let bytes = [0x00, 0xE0, 0x6A, 0x02, 0x12, 0x00];
+        let listing: Vec<String> = disassemble(&bytes, 0x200)
+            .map(|(addr, opcode, instr)| match instr {
+                Some(instr) => format!("{addr:#06x}: {opcode:04X}  {instr}"),
+                None => format!("{addr:#06x}: {opcode:04X}  ???"),
+            })
+            .collect();
+        assert_eq!(listing, [
+            "0x0200: 00E0  CLS",
+            "0x0202: 6A02  LD VA, 0x02",
+            "0x0204: 1200  JP 0x200",
+        ]);
+    }
+}
@@ -0,0 +1,307 @@
+use alloc::string::String;
+
+pub const DISPLAY_WIDTH: usize = 64;
+pub const DISPLAY_HEIGHT: usize = 32;
+
+// SUPER-CHIP high resolution mode
+pub const HIRES_DISPLAY_WIDTH: usize = 128;
+pub const HIRES_DISPLAY_HEIGHT: usize = 64;
+
+// XO-CHIP bitplanes
+pub const PLANE_COUNT: usize = 2;
+
+const PLANE_SIZE: usize = HIRES_DISPLAY_WIDTH * HIRES_DISPLAY_HEIGHT;
+
+/// Framebuffer, either 64x32 or 128x64 (SUPER-CHIP high resolution), made of two monochrome
+/// XO-CHIP bitplanes. Pixels are stored row by row using the width of the active resolution.
+///
+/// Drawing, clearing and scrolling only affect the selected planes. Plane 0 is selected by
+/// default, so programs unaware of planes only ever use plane 0.
+#[derive(Clone)]
+pub struct Display {
+    pixels: [[bool; PLANE_SIZE]; PLANE_COUNT],
+    width: usize,
+    height: usize,
+    /// Bitmask of the selected planes, bit 0 being plane 0
+    planes: u8,
+    /// Set whenever the pixels are modified
+    dirty: bool,
+}
+
+impl Display {
+    /// Create a new low resolution display with every pixel unset.
+    pub fn new() -> Self {
+        Self {
+            pixels: [[false; PLANE_SIZE]; PLANE_COUNT],
+            width: DISPLAY_WIDTH,
+            height: DISPLAY_HEIGHT,
+            planes: 0b01,
+            dirty: true,
+        }
+    }
+
+    /// Unset every pixel of every plane.
+    pub fn clear(&mut self) {
+        self.pixels = [[false; PLANE_SIZE]; PLANE_COUNT];
+        self.dirty = true;
+    }
+
+    /// Unset every pixel of the selected planes.
+    pub fn clear_selected(&mut self) {
+        for plane in 0..PLANE_COUNT {
+            if self.is_selected(plane) {
+                self.pixels[plane] = [false; PLANE_SIZE];
+            }
+        }
+        self.dirty = true;
+    }
+
+    /// Select the planes affected by drawing, clearing and scrolling. Bit 0 of `mask` selects
+    /// plane 0 and bit 1 selects plane 1; higher bits are ignored.
+    pub fn select_planes(&mut self, mask: u8) {
+        self.planes = mask & 0b11;
+    }
+
+    /// Bitmask of the selected planes.
+    pub fn selected_planes(&self) -> u8 {
+        self.planes
+    }
+
+    fn is_selected(&self, plane: usize) -> bool {
+        self.planes & (1 << plane) != 0
+    }
+
+    /// Whether the display has changed since the last call, clearing the flag.
+    pub fn take_dirty(&mut self) -> bool {
+        core::mem::take(&mut self.dirty)
+    }
+
+    /// Switch between low and high resolution. This clears the display.
+    pub fn set_hires(&mut self, hires: bool) {
+        (self.width, self.height) = if hires {
+            (HIRES_DISPLAY_WIDTH, HIRES_DISPLAY_HEIGHT)
+        } else {
+            (DISPLAY_WIDTH, DISPLAY_HEIGHT)
+        };
+        self.clear();
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Whether the display is in high resolution mode.
+    pub fn is_hires(&self) -> bool {
+        self.width == HIRES_DISPLAY_WIDTH
+    }
+
+    /// Whether the pixel at (x, y) is set. Pixels outside the active resolution are unset.
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        x < self.width && y < self.height && self.pixels[0][y * self.width + x]
+    }
+
+    /// Set or unset the pixel at (x, y). Pixels outside the active resolution are ignored.
+    pub fn set_pixel(&mut self, x: usize, y: usize, on: bool) {
+        if x < self.width && y < self.height {
+            self.pixels[0][y * self.width + x] = on;
+            self.dirty = true;
+        }
+    }
+
+    /// XOR a sprite onto the selected planes with its top-left corner at (x, y). The starting
+    /// position always wraps around the screen; pixels past the edges wrap too if `wrap` is
+    /// set, and are clipped otherwise. Returns the number of sprite rows that erased a set pixel
+    /// in any plane and the number of rows clipped at the bottom edge.
+    ///
+    /// With several planes selected, `sprite` holds an equally sized sprite for each of them
+    /// one after another, lowest plane first.
+    pub fn draw_sprite(&mut self, x: usize, y: usize, sprite: &[u8], wrap: bool) -> (usize, usize) {
+        self.draw(x, y, sprite, 1, wrap, true)
+    }
+
+    /// Like [`Display::draw_sprite`], but for a SUPER-CHIP 16x16 sprite, which has 2 bytes per
+    /// row.
+    pub fn draw_large_sprite(&mut self, x: usize, y: usize, sprite: &[u8], wrap: bool) -> (usize, usize) {
+        self.draw(x, y, sprite, 2, wrap, true)
+    }
+
+    /// Return what drawing a sprite `row_bytes` bytes wide would, without changing any pixels.
+    pub fn test_sprite(&mut self, x: usize, y: usize, sprite: &[u8], row_bytes: usize, wrap: bool) -> (usize, usize) {
+        self.draw(x, y, sprite, row_bytes, wrap, false)
+    }
+
+    /// Draw a sprite `row_bytes` bytes wide onto the selected planes, only checking for
+    /// collisions unless `write` is set.
+    fn draw(
+        &mut self,
+        x: usize,
+        y: usize,
+        sprite: &[u8],
+        row_bytes: usize,
+        wrap: bool,
+        write: bool,
+    ) -> (usize, usize) {
+        self.dirty |= write;
+        let selected = self.planes.count_ones() as usize;
+        if selected == 0 {
+            return (0, 0);
+        }
+
+        let len = sprite.len() / selected;
+        let mut collided = 0;
+        let mut clipped = 0;
+        let mut start = 0;
+        for plane in 0..PLANE_COUNT {
+            if self.is_selected(plane) {
+                let (plane_collided, plane_clipped) =
+                    self.draw_plane(plane, x, y, &sprite[start..start + len], row_bytes, wrap, write);
+                collided |= plane_collided;
+                clipped = plane_clipped;
+                start += len;
+            }
+        }
+        (collided.count_ones() as usize, clipped)
+    }
+
+    /// Draw a sprite with `row_bytes` bytes per row onto one plane, returning a bitmask of the
+    /// rows that erased a set pixel and the number of rows clipped at the bottom edge.
+    #[allow(clippy::too_many_arguments)]
+    fn draw_plane(
+        &mut self,
+        plane: usize,
+        x: usize,
+        y: usize,
+        sprite: &[u8],
+        row_bytes: usize,
+        wrap: bool,
+        write: bool,
+    ) -> (u32, usize) {
+        let x = x % self.width;
+        let y = y % self.height;
+        let rows = sprite.len() / row_bytes;
+        let mut collided = 0;
+
+        for (row, bytes) in sprite.chunks_exact(row_bytes).enumerate() {
+            let mut py = y + row;
+            if py >= self.height {
+                if !wrap {
+                    return (collided, rows - row);
+                }
+                py %= self.height;
+            }
+
+            for col in 0..8 * row_bytes {
+                let mut px = x + col;
+                if px >= self.width {
+                    if !wrap {
+                        break;
+                    }
+                    px %= self.width;
+                }
+
+                if bytes[col / 8] & (0x80 >> (col % 8)) != 0 {
+                    let pixel = &mut self.pixels[plane][py * self.width + px];
+                    if *pixel {
+                        collided |= 1 << row;
+                    }
+                    if write {
+                        *pixel = !*pixel;
+                    }
+                }
+            }
+        }
+
+        (collided, 0)
+    }
+
+    /// Shift the selected planes down by `n` rows, unsetting the rows scrolled in at the top.
+    pub fn scroll_down(&mut self, n: usize) {
+        self.dirty = true;
+        for plane in 0..PLANE_COUNT {
+            if !self.is_selected(plane) {
+                continue;
+            }
+            let pixels = &mut self.pixels[plane];
+            for y in (0..self.height).rev() {
+                for x in 0..self.width {
+                    pixels[y * self.width + x] = y >= n && pixels[(y - n) * self.width + x];
+                }
+            }
+        }
+    }
+
+    /// Shift the selected planes right by `n` columns, unsetting the columns scrolled in on the
+    /// left.
+    pub fn scroll_right(&mut self, n: usize) {
+        self.dirty = true;
+        for plane in 0..PLANE_COUNT {
+            if !self.is_selected(plane) {
+                continue;
+            }
+            for row in self.pixels[plane][..self.width * self.height].chunks_mut(self.width) {
+                for x in (0..row.len()).rev() {
+                    row[x] = x >= n && row[x - n];
+                }
+            }
+        }
+    }
+
+    /// Shift the selected planes left by `n` columns, unsetting the columns scrolled in on the
+    /// right.
+    pub fn scroll_left(&mut self, n: usize) {
+        self.dirty = true;
+        for plane in 0..PLANE_COUNT {
+            if !self.is_selected(plane) {
+                continue;
+            }
+            for row in self.pixels[plane][..self.width * self.height].chunks_mut(self.width) {
+                for x in 0..row.len() {
+                    row[x] = x + n < row.len() && row[x + n];
+                }
+            }
+        }
+    }
+
+    /// The pixels of plane 0 in the active resolution.
+    pub fn pixels(&self) -> &[bool] {
+        self.plane_pixels(0)
+    }
+
+    /// The pixels of `plane` in the active resolution. Panics if `plane` isn't 0 or 1.
+    pub fn plane_pixels(&self, plane: usize) -> &[bool] {
+        &self.pixels[plane][..self.width * self.height]
+    }
+
+    /// Render the display as one line per row, using `#` for set pixels and `.` for unset ones.
+    pub fn render_ascii(&self) -> String {
+        let mut out = String::with_capacity((self.width + 1) * self.height);
+        for row in self.pixels().chunks(self.width) {
+            out.extend(row.iter().map(|&pixel| if pixel { '#' } else { '.' }));
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// Displays are equal if they have the same resolution, selected planes and pixels, whether or
+/// not they are dirty.
+impl PartialEq for Display {
+    fn eq(&self, other: &Self) -> bool {
+        self.width == other.width
+            && self.height == other.height
+            && self.planes == other.planes
+            && self.pixels == other.pixels
+    }
+}
+
+impl Eq for Display {}
+
+impl Default for Display {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,198 @@
+use alloc::string::String;
+use core::fmt;
+
+/// Errors that can occur while loading a program into memory.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum LoadError {
+    /// The ROM doesn't fit in memory after the program start address.
+    RomTooLarge { size: usize, max: usize },
+    /// The font would overlap the program space starting at the program start address.
+    FontOverlapsProgram { font_base: u16 },
+    /// A custom font wasn't the expected size.
+    InvalidFontSize { size: usize, expected: usize },
+    /// A custom font doesn't fit in memory at `font_base`.
+    FontOutOfMemory { font_base: u16 },
+    /// The program start address is outside memory.
+    StartOutOfMemory { start: u16 },
+    /// The ROM file couldn't be read.
+    #[cfg(feature = "std")]
+    Io(std::io::Error),
+}
+
+/// `std::io::Error` isn't comparable, so I/O errors are considered equal if their kinds match.
+impl PartialEq for LoadError {
+    fn eq(&self, other: &Self) -> bool {
+        use LoadError::*;
+        match (self, other) {
+            (RomTooLarge { size: a, max: b }, RomTooLarge { size: c, max: d }) => a == c && b == d,
+            (FontOverlapsProgram { font_base: a }, FontOverlapsProgram { font_base: b }) => a == b,
+            (InvalidFontSize { size: a, expected: b }, InvalidFontSize { size: c, expected: d }) => a == c && b == d,
+            (FontOutOfMemory { font_base: a }, FontOutOfMemory { font_base: b }) => a == b,
+            (StartOutOfMemory { start: a }, StartOutOfMemory { start: b }) => a == b,
+            #[cfg(feature = "std")]
+            (Io(a), Io(b)) => a.kind() == b.kind(),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for LoadError {}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for LoadError {
+    fn from(e: std::io::Error) -> Self {
+        LoadError::Io(e)
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::RomTooLarge { size, max } => {
+                write!(f, "ROM is {size} bytes but at most {max} bytes fit in memory")
+            },
+            LoadError::FontOverlapsProgram { font_base } => {
+                write!(f, "font at {font_base:#06x} overlaps the program space")
+            },
+            LoadError::InvalidFontSize { size, expected } => {
+                write!(f, "font is {size} bytes but should be {expected} bytes")
+            },
+            LoadError::FontOutOfMemory { font_base } => {
+                write!(f, "font at {font_base:#06x} doesn't fit in memory")
+            },
+            LoadError::StartOutOfMemory { start } => {
+                write!(f, "start address {start:#06x} is outside memory")
+            },
+            #[cfg(feature = "std")]
+            LoadError::Io(e) => write!(f, "failed to read ROM: {e}"),
+        }
+    }
+}
+
+impl core::error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "std")]
+            LoadError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Errors that can occur while executing instructions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExecError {
+    /// The word at `pc` doesn't encode any known instruction.
+    InvalidOpcode { opcode: u16, pc: u16 },
+    /// The RET at `pc` was executed with an empty stack.
+    StackUnderflow { pc: u16 },
+    /// The CALL at `pc` was executed with all `depth` stack entries in use.
+    StackOverflow { pc: u16, depth: usize },
+    /// In strict mode, an instruction was fetched from `addr`, which was never written.
+    UninitializedRead { addr: u16 },
+    /// The program didn't halt within the given number of cycles.
+    CycleLimit { cycles: usize },
+    /// The pre-cycle hook aborted execution at `pc`.
+    Aborted { pc: u16 },
+}
+
+impl fmt::Display for ExecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExecError::InvalidOpcode { opcode, pc } => {
+                write!(f, "invalid opcode {opcode:#06x} at PC {pc:#06x}")
+            },
+            ExecError::StackUnderflow { pc } => {
+                write!(f, "return with an empty stack at PC {pc:#06x}")
+            },
+            ExecError::StackOverflow { pc, depth } => {
+                write!(f, "call with a full stack of depth {depth} at PC {pc:#06x}")
+            },
+            ExecError::UninitializedRead { addr } => {
+                write!(f, "instruction fetched from uninitialized memory at {addr:#06x}")
+            },
+            ExecError::CycleLimit { cycles } => {
+                write!(f, "program didn't halt within {cycles} cycles")
+            },
+            ExecError::Aborted { pc } => {
+                write!(f, "execution aborted at PC {pc:#06x}")
+            },
+        }
+    }
+}
+
+impl core::error::Error for ExecError {}
+
+/// Errors that can occur while decoding an instruction word.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The word doesn't encode any known instruction.
+    InvalidOpcode(u16),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::InvalidOpcode(opcode) => write!(f, "invalid opcode {opcode:#06x}"),
+        }
+    }
+}
+
+impl core::error::Error for DecodeError {}
+
+/// Errors that can occur while assembling a program. Lines are numbered from 1.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AsmError {
+    /// The line isn't a valid instruction, directive or label definition.
+    Syntax { line: usize },
+    /// A number is too large for its operand, e.g. a byte above 0xFF or an address above 0xFFF.
+    OutOfRange { line: usize },
+    /// The label is used but never defined.
+    UnknownLabel { line: usize, label: String },
+    /// The label is defined more than once.
+    DuplicateLabel { line: usize, label: String },
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AsmError::Syntax { line } => write!(f, "line {line}: syntax error"),
+            AsmError::OutOfRange { line } => write!(f, "line {line}: operand out of range"),
+            AsmError::UnknownLabel { line, label } => {
+                write!(f, "line {line}: unknown label `{label}`")
+            },
+            AsmError::DuplicateLabel { line, label } => {
+                write!(f, "line {line}: label `{label}` is already defined")
+            },
+        }
+    }
+}
+
+impl core::error::Error for AsmError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn exec_error_display_test() {
+        let cases = [
+            (ExecError::InvalidOpcode { opcode: 0x8008, pc: 0x204 }, "invalid opcode 0x8008 at PC 0x0204"),
+            (ExecError::StackUnderflow { pc: 0x3fe }, "return with an empty stack at PC 0x03fe"),
+            (ExecError::StackOverflow { pc: 0x240, depth: 16 }, "call with a full stack of depth 16 at PC 0x0240"),
+            (ExecError::UninitializedRead { addr: 0x400 }, "instruction fetched from uninitialized memory at 0x0400"),
+            (ExecError::CycleLimit { cycles: 1000 }, "program didn't halt within 1000 cycles"),
+            (ExecError::Aborted { pc: 0x20a }, "execution aborted at PC 0x020a"),
+        ];
+        for (error, message) in cases {
+            assert_eq!(error.to_string(), message);
+        }
+    }
+
+    #[test]
+    fn decode_error_display_test() {
+        assert_eq!(DecodeError::InvalidOpcode(0xe3ff).to_string(), "invalid opcode 0xe3ff");
+    }
+}
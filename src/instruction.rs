@@ -1,12 +1,8 @@
+use alloc::vec::Vec;
+use core::fmt;
 
-
-fn assemble_address(n0: u8, n1: u8, n2: u8) -> u16 {
-    (n0 as u16 & 0xf) | ((n1 as u16 & 0xf) << 4) | ((n2 as u16 & 0xf) << 8)
-}
-
-fn assemble_byte(k0: u8, k1: u8) -> u8 {
-    (k0 & 0xf) | ((k1 & 0xf) << 4)
-}
+use crate::assembler;
+use crate::error::{AsmError, DecodeError};
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum Instruction {
@@ -20,6 +16,11 @@ pub enum Instruction {
     /// #### Assembly Syntax
     /// `RET`
     Ret,
+    /// ### System Call
+    /// Call a machine code routine at addr on the original COSMAC VIP. Interpreters ignore it.
+    /// #### Assembly Syntax
+    /// `SYS    addr`
+    Sys(u16),
     /// ### Jump (immediate)
     /// Set PC = addr.
     /// #### Assembly Syntax
@@ -122,7 +123,9 @@ pub enum Instruction {
     /// `RND    Vx, byte`
     Rnd(u8, u8),
     /// ### Draw Sprite
-    /// Display n-byte sprite starting at memory location I at (Vx, Vy). VF = collision.
+    /// Display n-byte sprite starting at memory location I at (Vx, Vy). VF = collision. In
+    /// SUPER-CHIP high resolution mode, n = 0 displays a 16x16 sprite of 32 bytes. A sprite
+    /// running past the end of memory continues from address 0.
     /// #### Assembly Syntax
     /// `DRW    Vx, Vy, n`
     Drw(u8, u8, u8),
@@ -181,52 +184,508 @@ pub enum Instruction {
     /// #### Assembly Syntax
     /// `LD     Vx, [I]`
     LdRegMem(u8),
+
+    // SUPER-CHIP instructions
+
+    /// ### Low Resolution
+    /// Switch the display to 64x32 low resolution mode.
+    /// #### Assembly Syntax
+    /// `LOW`
+    Low,
+    /// ### High Resolution
+    /// Switch the display to 128x64 high resolution mode.
+    /// #### Assembly Syntax
+    /// `HIGH`
+    High,
+    /// ### Scroll Down
+    /// Scroll the display down by n pixels.
+    /// #### Assembly Syntax
+    /// `SCD    n`
+    ScrollDown(u8),
+    /// ### Scroll Right
+    /// Scroll the display right by 4 pixels.
+    /// #### Assembly Syntax
+    /// `SCR`
+    ScrollRight,
+    /// ### Scroll Left
+    /// Scroll the display left by 4 pixels.
+    /// #### Assembly Syntax
+    /// `SCL`
+    ScrollLeft,
+    /// ### Load (RPL flags, register)
+    /// Store registers V0 through Vx in the RPL user flags (x <= 7).
+    /// #### Assembly Syntax
+    /// `LD     R, Vx`
+    LdRplReg(u8),
+    /// ### Load (register, RPL flags)
+    /// Read registers V0 through Vx from the RPL user flags (x <= 7).
+    /// #### Assembly Syntax
+    /// `LD     Vx, R`
+    LdRegRpl(u8),
+    /// ### Load (big font character)
+    /// Set I = location of the 8x10 sprite for decimal digit Vx.
+    /// #### Assembly Syntax
+    /// `LD     HF, Vx`
+    LdHf(u8),
+
+    // XO-CHIP instructions
+
+    /// ### Load (index, long immediate)
+    /// Set I = addr, where addr is the full 16-bit word following the instruction.
+    /// #### Assembly Syntax
+    /// `LD     I, LONG addr`
+    LdILong(u16),
+    /// ### Select Planes
+    /// Select the display planes used by CLS, DRW and the scroll instructions, as a bitmask
+    /// n (0-3) where bit 0 is plane 0 and bit 1 is plane 1.
+    /// #### Assembly Syntax
+    /// `PLANE  n`
+    Plane(u8),
+    /// ### Load Audio Pattern
+    /// Copy the 16 bytes starting at I into the audio pattern buffer, a 128-bit waveform that
+    /// the buzzer plays while the sound timer is nonzero.
+    /// #### Assembly Syntax
+    /// `AUDIO`
+    Audio,
+    /// ### Set Pitch
+    /// Set the audio pattern's playback pitch to Vx.
+    /// #### Assembly Syntax
+    /// `PITCH  Vx`
+    Pitch(u8),
 }
 
+/// Mnemonic and opcode pattern of each kind of instruction.
+const PATTERNS: [(&str, &str); Instruction::KIND_COUNT] = [
+    ("CLS", "00E0"),
+    ("RET", "00EE"),
+    ("SYS", "0nnn"),
+    ("JP", "1nnn"),
+    ("CALL", "2nnn"),
+    ("SE", "3xkk"),
+    ("SNE", "4xkk"),
+    ("SE", "5xy0"),
+    ("LD", "6xkk"),
+    ("ADD", "7xkk"),
+    ("LD", "8xy0"),
+    ("OR", "8xy1"),
+    ("AND", "8xy2"),
+    ("XOR", "8xy3"),
+    ("ADD", "8xy4"),
+    ("SUB", "8xy5"),
+    ("SHR", "8xy6"),
+    ("SUBN", "8xy7"),
+    ("SHL", "8xyE"),
+    ("SNE", "9xy0"),
+    ("LD", "Annn"),
+    ("JP", "Bnnn"),
+    ("RND", "Cxkk"),
+    ("DRW", "Dxyn"),
+    ("SKP", "Ex9E"),
+    ("SKNP", "ExA1"),
+    ("LD", "Fx07"),
+    ("LD", "Fx0A"),
+    ("LD", "Fx15"),
+    ("LD", "Fx18"),
+    ("ADD", "Fx1E"),
+    ("LD", "Fx29"),
+    ("LD", "Fx33"),
+    ("LD", "Fx55"),
+    ("LD", "Fx65"),
+    ("LOW", "00FE"),
+    ("HIGH", "00FF"),
+    ("SCD", "00Cn"),
+    ("SCR", "00FB"),
+    ("SCL", "00FC"),
+    ("LD", "Fx75"),
+    ("LD", "Fx85"),
+    ("LD", "Fx30"),
+    ("LD", "F000 nnnn"),
+    ("PLANE", "Fn01"),
+    ("AUDIO", "F002"),
+    ("PITCH", "Fx3A"),
+];
+
 impl Instruction {
+    /// Number of distinct instructions, i.e. the number of values [`Instruction::kind`] can
+    /// return.
+    pub const KIND_COUNT: usize = 47;
+
+    /// Name of each kind of instruction, indexed by [`Instruction::kind`].
+    pub const KIND_NAMES: [&'static str; Self::KIND_COUNT] = [
+        "Cls",
+        "Ret",
+        "Sys",
+        "JpImm",
+        "Call",
+        "SeImm",
+        "SneImm",
+        "SeReg",
+        "LdImm",
+        "AddImm",
+        "LdReg",
+        "OrReg",
+        "AndReg",
+        "XorReg",
+        "AddReg",
+        "SubReg",
+        "Shr",
+        "Subn",
+        "Shl",
+        "SneReg",
+        "LdI",
+        "JpReg",
+        "Rnd",
+        "Drw",
+        "Skp",
+        "Sknp",
+        "LdRegDt",
+        "LdRegK",
+        "LdDtReg",
+        "LdStReg",
+        "AddI",
+        "LdF",
+        "LdB",
+        "LdMemReg",
+        "LdRegMem",
+        "Low",
+        "High",
+        "ScrollDown",
+        "ScrollRight",
+        "ScrollLeft",
+        "LdRplReg",
+        "LdRegRpl",
+        "LdHf",
+        "LdILong",
+        "Plane",
+        "Audio",
+        "Pitch",
+    ];
+
+    /// Index identifying which instruction this is regardless of its operands, in the order the
+    /// variants are declared.
+    pub fn kind(&self) -> usize {
+        use Instruction::*;
+        match self {
+            Cls => 0,
+            Ret => 1,
+            Sys(..) => 2,
+            JpImm(..) => 3,
+            Call(..) => 4,
+            SeImm(..) => 5,
+            SneImm(..) => 6,
+            SeReg(..) => 7,
+            LdImm(..) => 8,
+            AddImm(..) => 9,
+            LdReg(..) => 10,
+            OrReg(..) => 11,
+            AndReg(..) => 12,
+            XorReg(..) => 13,
+            AddReg(..) => 14,
+            SubReg(..) => 15,
+            Shr(..) => 16,
+            Subn(..) => 17,
+            Shl(..) => 18,
+            SneReg(..) => 19,
+            LdI(..) => 20,
+            JpReg(..) => 21,
+            Rnd(..) => 22,
+            Drw(..) => 23,
+            Skp(..) => 24,
+            Sknp(..) => 25,
+            LdRegDt(..) => 26,
+            LdRegK(..) => 27,
+            LdDtReg(..) => 28,
+            LdStReg(..) => 29,
+            AddI(..) => 30,
+            LdF(..) => 31,
+            LdB(..) => 32,
+            LdMemReg(..) => 33,
+            LdRegMem(..) => 34,
+            Low => 35,
+            High => 36,
+            ScrollDown(..) => 37,
+            ScrollRight => 38,
+            ScrollLeft => 39,
+            LdRplReg(..) => 40,
+            LdRegRpl(..) => 41,
+            LdHf(..) => 42,
+            LdILong(..) => 43,
+            Plane(..) => 44,
+            Audio => 45,
+            Pitch(..) => 46,
+        }
+    }
+
+    /// Name of the instruction's variant, such as `"JpImm"`.
+    pub fn name(&self) -> &'static str {
+        Self::KIND_NAMES[self.kind()]
+    }
+
+    /// Every instruction's mnemonic and opcode pattern, such as `("DRW", "Dxyn")`, in the same
+    /// order as [`Instruction::kind`]. The 35 original Chip-8 instructions come first, followed
+    /// by the SUPER-CHIP and XO-CHIP ones.
+    pub fn all_patterns() -> Vec<(&'static str, &'static str)> {
+        PATTERNS.to_vec()
+    }
+
+    /// Decode an instruction word, panicking if it isn't a valid instruction. See
+    /// [`Instruction::try_decode`] for a non-panicking version.
     pub fn decode(source: u16) -> Instruction {
-        let n0 = ((source >> 0 ) & 0xf) as u8;
-        let n1 = ((source >> 4 ) & 0xf) as u8;
-        let n2 = ((source >> 8 ) & 0xf) as u8;
-        let n3 = ((source >> 12) & 0xf) as u8;
-
-        match (n3, n2, n1, n0) {
-            (0x0, 0x0, 0xE, 0x0) => Instruction::Cls,
-            (0x0, 0x0, 0xE, 0xE) => Instruction::Ret,
-            (0x1,  n2,  n1,  n0) => Instruction::JpImm(assemble_address(n0, n1, n2)),
-            (0x2,  n2,  n1,  n0) => Instruction::Call(assemble_address(n0, n1, n2)),
-            (0x3,   x,  k1,  k0) => Instruction::SeImm(x, assemble_byte(k0, k1)),
-            (0x4,   x,  k1,  k0) => Instruction::SneImm(x, assemble_byte(k0, k1)),
-            (0x5,   x,   y, 0x0) => Instruction::SeReg(x, y),
-            (0x6,   x,  k1,  k0) => Instruction::LdImm(x, assemble_byte(k0, k1)),
-            (0x7,   x,  k1,  k0) => Instruction::AddImm(x, assemble_byte(k0, k1)),
-            (0x8,   x,   y, 0x0) => Instruction::LdReg(x, y),
-            (0x8,   x,   y, 0x1) => Instruction::OrReg(x, y),
-            (0x8,   x,   y, 0x2) => Instruction::AndReg(x, y),
-            (0x8,   x,   y, 0x3) => Instruction::XorReg(x, y),
-            (0x8,   x,   y, 0x4) => Instruction::AddReg(x, y),
-            (0x8,   x,   y, 0x5) => Instruction::SubReg(x, y),
-            (0x8,   x,   y, 0x6) => Instruction::Shr(x, y),
-            (0x8,   x,   y, 0x7) => Instruction::Subn(x, y),
-            (0x8,   x,   y, 0xE) => Instruction::Shl(x, y),
-            (0x9,   x,   y, 0x0) => Instruction::SneReg(x, y),
-            (0xA,  n2,  n1,  n0) => Instruction::LdI(assemble_address(n0, n1, n2)),
-            (0xB,  n2,  n1,  n0) => Instruction::JpReg(assemble_address(n0, n1, n2)),
-            (0xC,   x,  k1,  k0) => Instruction::Rnd(x, assemble_byte(k0, k1)),
-            (0xD,   x,   y,   n) => Instruction::Drw(x, y, n),
-            (0xE,   x, 0x9, 0xE) => Instruction::Skp(x),
-            (0xE,   x, 0xA, 0x1) => Instruction::Sknp(x),
-            (0xF,   x, 0x0, 0x7) => Instruction::LdRegDt(x),
-            (0xF,   x, 0x0, 0xA) => Instruction::LdRegK(x),
-            (0xF,   x, 0x1, 0x5) => Instruction::LdDtReg(x),
-            (0xF,   x, 0x1, 0x8) => Instruction::LdStReg(x),
-            (0xF,   x, 0x1, 0xE) => Instruction::AddI(x),
-            (0xF,   x, 0x2, 0x9) => Instruction::LdF(x),
-            (0xF,   x, 0x3, 0x3) => Instruction::LdB(x),
-            (0xF,   x, 0x5, 0x5) => Instruction::LdMemReg(x),
-            (0xF,   x, 0x6, 0x5) => Instruction::LdRegMem(x),
-
-            _ => panic!("decoded invalid instruction: {source:#04x}"),
+        match Self::try_decode(source) {
+            Ok(instr) => instr,
+            Err(_) => panic!("decoded invalid instruction: {source:#06x}"),
+        }
+    }
+
+    /// Decode an instruction word. Double-width instructions can't be decoded from a single
+    /// word; use [`Instruction::try_decode_long`] for those.
+    pub fn try_decode(source: u16) -> Result<Instruction, DecodeError> {
+        use Instruction::*;
+        let x = ((source >> 8) & 0xf) as u8;
+        let y = ((source >> 4) & 0xf) as u8;
+        let n = (source & 0xf) as u8;
+        let byte = (source & 0xff) as u8;
+        let addr = source & 0xfff;
+
+        // Dispatch on the high nibble, then on the low nibble or byte for the groups that
+        // share a prefix
+        let instr = match source >> 12 {
+            0x0 => Self::decode_0(addr),
+            0x1 => Some(JpImm(addr)),
+            0x2 => Some(Call(addr)),
+            0x3 => Some(SeImm(x, byte)),
+            0x4 => Some(SneImm(x, byte)),
+            0x5 if n == 0x0 => Some(SeReg(x, y)),
+            0x6 => Some(LdImm(x, byte)),
+            0x7 => Some(AddImm(x, byte)),
+            0x8 => Self::decode_8(x, y, n),
+            0x9 if n == 0x0 => Some(SneReg(x, y)),
+            0xA => Some(LdI(addr)),
+            0xB => Some(JpReg(addr)),
+            0xC => Some(Rnd(x, byte)),
+            0xD => Some(Drw(x, y, n)),
+            0xE => Self::decode_e(x, byte),
+            0xF => Self::decode_f(x, byte),
+            _ => None,
+        };
+        instr.ok_or(DecodeError::InvalidOpcode(source))
+    }
+
+    /// Decode a word of the form 0nnn.
+    fn decode_0(addr: u16) -> Option<Instruction> {
+        use Instruction::*;
+        match addr {
+            0x0E0 => Some(Cls),
+            0x0EE => Some(Ret),
+            0x0C0..=0x0CF => Some(ScrollDown((addr & 0xf) as u8)),
+            0x0FB => Some(ScrollRight),
+            0x0FC => Some(ScrollLeft),
+            0x0FE => Some(Low),
+            0x0FF => Some(High),
+            _ => Some(Sys(addr)),
+        }
+    }
+
+    /// Decode a word of the form 8xyn.
+    fn decode_8(x: u8, y: u8, n: u8) -> Option<Instruction> {
+        use Instruction::*;
+        match n {
+            0x0 => Some(LdReg(x, y)),
+            0x1 => Some(OrReg(x, y)),
+            0x2 => Some(AndReg(x, y)),
+            0x3 => Some(XorReg(x, y)),
+            0x4 => Some(AddReg(x, y)),
+            0x5 => Some(SubReg(x, y)),
+            0x6 => Some(Shr(x, y)),
+            0x7 => Some(Subn(x, y)),
+            0xE => Some(Shl(x, y)),
+            _ => None,
+        }
+    }
+
+    /// Decode a word of the form Exkk.
+    fn decode_e(x: u8, byte: u8) -> Option<Instruction> {
+        match byte {
+            0x9E => Some(Instruction::Skp(x)),
+            0xA1 => Some(Instruction::Sknp(x)),
+            _ => None,
+        }
+    }
+
+    /// Decode a word of the form Fxkk.
+    fn decode_f(x: u8, byte: u8) -> Option<Instruction> {
+        use Instruction::*;
+        match byte {
+            // There are only two planes, so only the low 2 bits of the mask can be set
+            0x01 if x <= 3 => Some(Plane(x)),
+            0x02 if x == 0 => Some(Audio),
+            0x07 => Some(LdRegDt(x)),
+            0x0A => Some(LdRegK(x)),
+            0x15 => Some(LdDtReg(x)),
+            0x18 => Some(LdStReg(x)),
+            0x1E => Some(AddI(x)),
+            0x29 => Some(LdF(x)),
+            0x3A => Some(Pitch(x)),
+            0x30 => Some(LdHf(x)),
+            0x33 => Some(LdB(x)),
+            0x55 => Some(LdMemReg(x)),
+            0x65 => Some(LdRegMem(x)),
+            0x75 => Some(LdRplReg(x)),
+            0x85 => Some(LdRegRpl(x)),
+            _ => None,
+        }
+    }
+
+    /// Whether `source` is the first word of a double-width XO-CHIP instruction, whose operand
+    /// is the word that follows it.
+    pub fn is_long(source: u16) -> bool {
+        source == 0xF000
+    }
+
+    /// Decode an instruction word followed by `next`, returning the instruction and its length
+    /// in bytes. `next` is only used by double-width instructions, which are 4 bytes long.
+    pub fn try_decode_long(source: u16, next: u16) -> Result<(Instruction, u16), DecodeError> {
+        let instr = if Self::is_long(source) { Instruction::LdILong(next) } else { Self::try_decode(source)? };
+        let size = instr.size();
+        Ok((instr, size))
+    }
+
+    /// Length of the instruction in bytes: 4 for a double-width instruction and 2 otherwise.
+    pub fn size(&self) -> u16 {
+        match self {
+            Instruction::LdILong(..) => 4,
+            _ => 2,
+        }
+    }
+
+    /// Encode the instruction as an instruction word. Operands are masked to the width of
+    /// their field. For a double-width instruction this is the first word; the operand word
+    /// follows it.
+    pub fn encode(&self) -> u16 {
+        use Instruction::*;
+        let x = |x: u8| (x as u16 & 0xf) << 8;
+        let y = |y: u8| (y as u16 & 0xf) << 4;
+        let n = |n: u8| n as u16 & 0xf;
+        let addr = |addr: u16| addr & 0xfff;
+
+        match *self {
+            Cls => 0x00E0,
+            Ret => 0x00EE,
+            Sys(a) => addr(a),
+            JpImm(a) => 0x1000 | addr(a),
+            Call(a) => 0x2000 | addr(a),
+            SeImm(vx, byte) => 0x3000 | x(vx) | byte as u16,
+            SneImm(vx, byte) => 0x4000 | x(vx) | byte as u16,
+            SeReg(vx, vy) => 0x5000 | x(vx) | y(vy),
+            LdImm(vx, byte) => 0x6000 | x(vx) | byte as u16,
+            AddImm(vx, byte) => 0x7000 | x(vx) | byte as u16,
+            LdReg(vx, vy) => 0x8000 | x(vx) | y(vy),
+            OrReg(vx, vy) => 0x8001 | x(vx) | y(vy),
+            AndReg(vx, vy) => 0x8002 | x(vx) | y(vy),
+            XorReg(vx, vy) => 0x8003 | x(vx) | y(vy),
+            AddReg(vx, vy) => 0x8004 | x(vx) | y(vy),
+            SubReg(vx, vy) => 0x8005 | x(vx) | y(vy),
+            Shr(vx, vy) => 0x8006 | x(vx) | y(vy),
+            Subn(vx, vy) => 0x8007 | x(vx) | y(vy),
+            Shl(vx, vy) => 0x800E | x(vx) | y(vy),
+            SneReg(vx, vy) => 0x9000 | x(vx) | y(vy),
+            LdI(a) => 0xA000 | addr(a),
+            JpReg(a) => 0xB000 | addr(a),
+            Rnd(vx, byte) => 0xC000 | x(vx) | byte as u16,
+            Drw(vx, vy, rows) => 0xD000 | x(vx) | y(vy) | n(rows),
+            Skp(vx) => 0xE09E | x(vx),
+            Sknp(vx) => 0xE0A1 | x(vx),
+            LdRegDt(vx) => 0xF007 | x(vx),
+            LdRegK(vx) => 0xF00A | x(vx),
+            LdDtReg(vx) => 0xF015 | x(vx),
+            LdStReg(vx) => 0xF018 | x(vx),
+            AddI(vx) => 0xF01E | x(vx),
+            LdF(vx) => 0xF029 | x(vx),
+            LdB(vx) => 0xF033 | x(vx),
+            LdMemReg(vx) => 0xF055 | x(vx),
+            LdRegMem(vx) => 0xF065 | x(vx),
+            Low => 0x00FE,
+            High => 0x00FF,
+            ScrollDown(rows) => 0x00C0 | n(rows),
+            ScrollRight => 0x00FB,
+            ScrollLeft => 0x00FC,
+            LdRplReg(vx) => 0xF075 | x(vx),
+            LdRegRpl(vx) => 0xF085 | x(vx),
+            LdHf(vx) => 0xF030 | x(vx),
+            LdILong(_) => 0xF000,
+            Plane(planes) => 0xF001 | x(planes),
+            Audio => 0xF002,
+            Pitch(vx) => 0xF03A | x(vx),
+        }
+    }
+
+    /// Parse a single instruction in the assembly syntax produced by the `Display` impl, e.g.
+    /// `LD V0, 0x05`. Mnemonics and register names are case-insensitive. Labels aren't
+    /// supported; use [`assemble`](crate::assemble) for whole programs.
+    pub fn from_asm(line: &str) -> Result<Instruction, AsmError> {
+        assembler::parse_instruction(line, 1, &|_| None)
+    }
+}
+
+/// Encode `instr` as the two bytes [`Cpu::cycle`](crate::Cpu::cycle) fetches, high byte
+/// first, e.g. for writing an instruction into memory in a test. For a double-width
+/// instruction these are the first two bytes.
+pub fn asm_word(instr: Instruction) -> [u8; 2] {
+    instr.encode().to_be_bytes()
+}
+
+impl fmt::Display for Instruction {
+    /// Format the instruction using its assembly syntax.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use Instruction::*;
+        match *self {
+            Cls => write!(f, "CLS"),
+            Ret => write!(f, "RET"),
+            Sys(addr) => write!(f, "SYS 0x{addr:03X}"),
+            JpImm(addr) => write!(f, "JP 0x{addr:03X}"),
+            Call(addr) => write!(f, "CALL 0x{addr:03X}"),
+            SeImm(x, byte) => write!(f, "SE V{x:X}, 0x{byte:02X}"),
+            SneImm(x, byte) => write!(f, "SNE V{x:X}, 0x{byte:02X}"),
+            SeReg(x, y) => write!(f, "SE V{x:X}, V{y:X}"),
+            LdImm(x, byte) => write!(f, "LD V{x:X}, 0x{byte:02X}"),
+            AddImm(x, byte) => write!(f, "ADD V{x:X}, 0x{byte:02X}"),
+            LdReg(x, y) => write!(f, "LD V{x:X}, V{y:X}"),
+            OrReg(x, y) => write!(f, "OR V{x:X}, V{y:X}"),
+            AndReg(x, y) => write!(f, "AND V{x:X}, V{y:X}"),
+            XorReg(x, y) => write!(f, "XOR V{x:X}, V{y:X}"),
+            AddReg(x, y) => write!(f, "ADD V{x:X}, V{y:X}"),
+            SubReg(x, y) => write!(f, "SUB V{x:X}, V{y:X}"),
+            Shr(x, y) => write!(f, "SHR V{x:X}, V{y:X}"),
+            Subn(x, y) => write!(f, "SUBN V{x:X}, V{y:X}"),
+            Shl(x, y) => write!(f, "SHL V{x:X}, V{y:X}"),
+            SneReg(x, y) => write!(f, "SNE V{x:X}, V{y:X}"),
+            LdI(addr) => write!(f, "LD I, 0x{addr:03X}"),
+            JpReg(addr) => write!(f, "JP V0, 0x{addr:03X}"),
+            Rnd(x, byte) => write!(f, "RND V{x:X}, 0x{byte:02X}"),
+            Drw(x, y, n) => write!(f, "DRW V{x:X}, V{y:X}, {n}"),
+            Skp(x) => write!(f, "SKP V{x:X}"),
+            Sknp(x) => write!(f, "SKNP V{x:X}"),
+            LdRegDt(x) => write!(f, "LD V{x:X}, DT"),
+            LdRegK(x) => write!(f, "LD V{x:X}, K"),
+            LdDtReg(x) => write!(f, "LD DT, V{x:X}"),
+            LdStReg(x) => write!(f, "LD ST, V{x:X}"),
+            AddI(x) => write!(f, "ADD I, V{x:X}"),
+            LdF(x) => write!(f, "LD F, V{x:X}"),
+            LdB(x) => write!(f, "LD B, V{x:X}"),
+            LdMemReg(x) => write!(f, "LD [I], V{x:X}"),
+            LdRegMem(x) => write!(f, "LD V{x:X}, [I]"),
+            Low => write!(f, "LOW"),
+            High => write!(f, "HIGH"),
+            ScrollDown(n) => write!(f, "SCD {n}"),
+            ScrollRight => write!(f, "SCR"),
+            ScrollLeft => write!(f, "SCL"),
+            LdRplReg(x) => write!(f, "LD R, V{x:X}"),
+            LdRegRpl(x) => write!(f, "LD V{x:X}, R"),
+            LdHf(x) => write!(f, "LD HF, V{x:X}"),
+            LdILong(addr) => write!(f, "LD I, LONG 0x{addr:04X}"),
+            Plane(n) => write!(f, "PLANE {n}"),
+            Audio => write!(f, "AUDIO"),
+            Pitch(x) => write!(f, "PITCH V{x:X}"),
         }
     }
 }
@@ -241,6 +700,13 @@ mod tests {
         let decode_table: Vec<(u16, Instruction)> = vec![
             (0x00E0, Cls),
             (0x00EE, Ret),
+            (0x0123, Sys(0x123)),
+            (0x0000, Sys(0x000)),
+            (0x00C3, ScrollDown(3)),
+            (0x00FB, ScrollRight),
+            (0x00FC, ScrollLeft),
+            (0x00FE, Low),
+            (0x00FF, High),
             (0x10ff, JpImm(0x0ff)),
             (0x2fcc, Call(0xfcc)),
             (0x3381, SeImm(3, 0x81)),
@@ -250,10 +716,178 @@ mod tests {
 
             (0x8980, LdReg(9, 8)),
             (0xA123, LdI(0x123)),
+            (0xF730, LdHf(7)),
+            (0xF375, LdRplReg(3)),
+            (0xF885, LdRegRpl(8)),
+            (0xF201, Plane(2)),
+            (0xF002, Audio),
+            (0xF43A, Pitch(4)),
         ];
 
         for (bytes, instr) in decode_table {
             assert_eq!(Instruction::decode(bytes), instr)
         }
     }
+
+    #[test]
+    fn try_decode_invalid_test() {
+        assert_eq!(Instruction::try_decode(0x5121), Err(DecodeError::InvalidOpcode(0x5121)));
+        assert_eq!(Instruction::try_decode(0xF0FF), Err(DecodeError::InvalidOpcode(0xF0FF)));
+    }
+
+    #[test]
+    fn try_decode_invalid_e_f_test() {
+        // Only Ex9E and ExA1 use the E prefix
+        for word in [0xE000, 0xE012, 0xE09F, 0xE0A2] {
+            assert_eq!(Instruction::try_decode(word), Err(DecodeError::InvalidOpcode(word)));
+        }
+        // Unknown low bytes with the F prefix
+        for word in [0xF099, 0xF100, 0xF02A, 0xF5FF, 0xF102, 0xF401] {
+            assert_eq!(Instruction::try_decode(word), Err(DecodeError::InvalidOpcode(word)));
+        }
+    }
+
+    #[test]
+    fn try_decode_all_test() {
+        // Every word either decodes or is rejected with itself as the invalid opcode
+        for word in 0..=0xFFFF {
+            match Instruction::try_decode(word) {
+                Ok(instr) => {
+                    assert!(instr.kind() < Instruction::KIND_COUNT);
+                    assert!(!instr.to_string().is_empty());
+                },
+                Err(e) => assert_eq!(e, DecodeError::InvalidOpcode(word)),
+            }
+        }
+        assert_eq!(Instruction::try_decode(0x812F), Err(DecodeError::InvalidOpcode(0x812F)));
+        assert_eq!(Instruction::try_decode(0xF300), Err(DecodeError::InvalidOpcode(0xF300)));
+    }
+
+    #[test]
+    fn try_decode_long_test() {
+        use Instruction::*;
+        assert_eq!(Instruction::try_decode_long(0xF000, 0x1234), Ok((LdILong(0x1234), 4)));
+        assert_eq!(Instruction::try_decode_long(0xA123, 0x1234), Ok((LdI(0x123), 2)));
+        assert_eq!(Instruction::try_decode(0xF000), Err(DecodeError::InvalidOpcode(0xF000)));
+    }
+
+    #[test]
+    fn kind_test() {
+        use Instruction::*;
+        assert_eq!(Cls.kind(), 0);
+        assert_eq!(Pitch(1).kind(), Instruction::KIND_COUNT - 1);
+        assert_eq!(JpImm(0x200).kind(), JpImm(0x300).kind());
+        assert_eq!(Sys(0x123).name(), "Sys");
+        assert_eq!(Drw(1, 2, 3).name(), "Drw");
+        assert_eq!(LdRegRpl(0).name(), "LdRegRpl");
+    }
+
+    #[test]
+    fn encode_test() {
+        // Every valid word encodes back to itself
+        for word in 0..=0xFFFF {
+            if let Ok(instr) = Instruction::try_decode(word) {
+                assert_eq!(instr.encode(), word, "{instr}");
+            }
+        }
+        assert_eq!(Instruction::LdILong(0x1234).encode(), 0xF000);
+    }
+
+    #[test]
+    fn encode_decode_variants_test() {
+        use Instruction::*;
+        // One of every variant, in kind order. The array length makes adding a variant without
+        // listing it here a compile error.
+        const VARIANTS: [Instruction; Instruction::KIND_COUNT] = [
+            Cls, Ret, Sys(0x2a4), JpImm(0x3f0), Call(0xabc), SeImm(1, 0x80), SneImm(0xe, 0x7f),
+            SeReg(2, 0xd), LdImm(0xf, 0xff), AddImm(3, 0x01), LdReg(4, 5), OrReg(6, 7),
+            AndReg(8, 9), XorReg(0xa, 0xb), AddReg(0xc, 0xd), SubReg(0xe, 0xf), Shr(0, 1),
+            Subn(2, 3), Shl(4, 5), SneReg(6, 7), LdI(0xfff), JpReg(0x001), Rnd(8, 0x0f),
+            Drw(9, 0xa, 0), Skp(0xb), Sknp(0xc), LdRegDt(0xd), LdRegK(0xe), LdDtReg(0xf),
+            LdStReg(1), AddI(2), LdF(3), LdB(4), LdMemReg(5), LdRegMem(6), Low, High,
+            ScrollDown(0xf), ScrollRight, ScrollLeft, LdRplReg(7), LdRegRpl(0), LdHf(9),
+            LdILong(0xbeef), Plane(3), Audio, Pitch(0xa),
+        ];
+
+        for (kind, instr) in VARIANTS.into_iter().enumerate() {
+            assert_eq!(instr.kind(), kind, "{instr}");
+            let operand = match instr {
+                LdILong(addr) => addr,
+                _ => 0,
+            };
+            let size = instr.size();
+            let decoded = Instruction::try_decode_long(instr.encode(), operand);
+            assert_eq!(decoded, Ok((instr, size)));
+        }
+    }
+
+    #[test]
+    fn all_patterns_test() {
+        let patterns = Instruction::all_patterns();
+        assert_eq!(patterns.len(), Instruction::KIND_COUNT);
+        // The original instructions come first, up to LD Vx, [I]
+        assert_eq!(patterns.iter().position(|&p| p == ("LD", "Fx65")), Some(34));
+        assert!(patterns.contains(&("DRW", "Dxyn")));
+        assert!(patterns.contains(&("SCD", "00Cn")));
+        assert!(patterns.contains(&("LD", "F000 nnnn")));
+
+        // Each pattern's first word, with its operands zeroed, decodes to that kind
+        for (kind, (_, pattern)) in patterns.iter().enumerate() {
+            let word = pattern[..4].replace(|c: char| c.is_ascii_lowercase(), "0");
+            let word = u16::from_str_radix(&word, 16).unwrap();
+            let (instr, _) = Instruction::try_decode_long(word, 0).unwrap();
+            assert_eq!(instr.kind(), kind, "{pattern}");
+        }
+    }
+
+    #[test]
+    fn asm_word_test() {
+        use Instruction::*;
+        assert_eq!(asm_word(LdImm(0, 5)), [0x60, 0x05]);
+        assert_eq!(asm_word(Drw(1, 2, 3)), [0xD1, 0x23]);
+        // The bytes are in the order cycle reads them, so they decode back to the same word
+        for word in 0..=0xFFFF {
+            if let Ok(instr) = Instruction::try_decode(word) {
+                assert_eq!(asm_word(instr), [(word >> 8) as u8, word as u8]);
+            }
+        }
+    }
+
+    #[test]
+    fn from_asm_test() {
+        use Instruction::*;
+        assert_eq!(Instruction::from_asm("LD V0, 0x05"), Ok(LdImm(0, 5)));
+        assert_eq!(Instruction::from_asm("  drw va, vb, 15  "), Ok(Drw(0xa, 0xb, 15)));
+        assert_eq!(Instruction::from_asm("LD [I], VF"), Ok(LdMemReg(0xf)));
+        assert_eq!(Instruction::from_asm("SHR V3"), Ok(Shr(3, 3)));
+        assert_eq!(Instruction::from_asm("JP V0, 0x300"), Ok(JpReg(0x300)));
+        assert_eq!(Instruction::from_asm("LD I, LONG 0x1234"), Ok(LdILong(0x1234)));
+
+        // Every valid word's listing parses back to the same instruction
+        for word in 0..=0xFFFF {
+            if let Ok(instr) = Instruction::try_decode(word) {
+                assert_eq!(Instruction::from_asm(&instr.to_string()), Ok(instr));
+            }
+        }
+    }
+
+    #[test]
+    fn display_test() {
+        use Instruction::*;
+        let display_table = [
+            (Cls, "CLS"),
+            (JpImm(0x200), "JP 0x200"),
+            (SeImm(3, 0x81), "SE V3, 0x81"),
+            (AddReg(0xa, 0xb), "ADD VA, VB"),
+            (JpReg(0x3f0), "JP V0, 0x3F0"),
+            (Drw(1, 2, 5), "DRW V1, V2, 5"),
+            (LdMemReg(0xf), "LD [I], VF"),
+            (ScrollDown(4), "SCD 4"),
+            (LdILong(0x1234), "LD I, LONG 0x1234"),
+        ];
+
+        for (instr, text) in display_table {
+            assert_eq!(instr.to_string(), text);
+        }
+    }
 }
\ No newline at end of file
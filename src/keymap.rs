@@ -0,0 +1,42 @@
+/// The QWERTY key for each Chip-8 key, indexed by Chip-8 key. The hex keypad is mapped to the
+/// left side of the keyboard, keeping its layout:
+///
+/// ```text
+/// 1 2 3 C        1 2 3 4
+/// 4 5 6 D   <-   Q W E R
+/// 7 8 9 E        A S D F
+/// A 0 B F        Z X C V
+/// ```
+pub const QWERTY_KEYS: [char; 16] = [
+    'x', '1', '2', '3',     // 0 1 2 3
+    'q', 'w', 'e', 'a',     // 4 5 6 7
+    's', 'd', 'z', 'c',     // 8 9 A B
+    '4', 'r', 'f', 'v',     // C D E F
+];
+
+/// The Chip-8 key (0x0-0xF) a QWERTY key is mapped to in [`QWERTY_KEYS`], ignoring case, or
+/// `None` if it isn't part of the keypad.
+pub fn qwerty_to_chip8(key: char) -> Option<u8> {
+    let key = key.to_ascii_lowercase();
+    QWERTY_KEYS.iter().position(|&k| k == key).map(|i| i as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn qwerty_to_chip8_test() {
+        assert_eq!(qwerty_to_chip8('q'), Some(0x4));
+        assert_eq!(qwerty_to_chip8('c'), Some(0xB));
+        assert_eq!(qwerty_to_chip8('1'), Some(0x1));
+        assert_eq!(qwerty_to_chip8('x'), Some(0x0));
+        assert_eq!(qwerty_to_chip8('V'), Some(0xF));
+        assert_eq!(qwerty_to_chip8('5'), None);
+        assert_eq!(qwerty_to_chip8('p'), None);
+
+        for (key, &qwerty) in QWERTY_KEYS.iter().enumerate() {
+            assert_eq!(qwerty_to_chip8(qwerty), Some(key as u8));
+        }
+    }
+}
@@ -0,0 +1,40 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+mod assembler;
+mod builder;
+mod cpu;
+mod disassembler;
+mod display;
+mod error;
+mod instruction;
+mod keymap;
+mod machine;
+mod macros;
+mod monitor;
+mod quirks;
+mod render;
+mod rng;
+mod scheduler;
+#[cfg(feature = "wasm")]
+mod wasm;
+
+pub use assembler::assemble;
+pub use builder::CpuBuilder;
+pub use cpu::{Chip8, Control, Cpu, CpuState, Diagnostic, FrameEvents, KeyEvent, PreCycleHook, StateChange, TraceHook};
+pub use disassembler::disassemble;
+pub use display::{DISPLAY_HEIGHT, DISPLAY_WIDTH, HIRES_DISPLAY_HEIGHT, HIRES_DISPLAY_WIDTH, PLANE_COUNT};
+pub use error::{AsmError, DecodeError, ExecError, LoadError};
+pub use instruction::{asm_word, Instruction};
+pub use keymap::{qwerty_to_chip8, QWERTY_KEYS};
+pub use machine::Machine;
+#[doc(hidden)]
+pub use macros::rom_from_statements as __rom_from_statements;
+pub use monitor::Monitor;
+pub use quirks::Quirks;
+pub use render::render_rgba;
+pub use rng::{FixedRng, Rng, RngClone, XorShift};
+pub use scheduler::Scheduler;
+#[cfg(feature = "wasm")]
+pub use wasm::WasmCpu;
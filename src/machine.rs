@@ -0,0 +1,126 @@
+use core::time::Duration;
+
+use crate::cpu::Cpu;
+use crate::error::{ExecError, LoadError};
+use crate::scheduler::Scheduler;
+
+/// A complete Chip-8 machine that takes care of running cycles and ticking the timers. Load a
+/// ROM, call [`Machine::update`] with the time since the last update, and read back the
+/// display and buzzer.
+pub struct Machine {
+    scheduler: Scheduler,
+}
+
+impl Default for Machine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Machine {
+    /// Create a machine running at the CPU's clock rate, 700 instructions per second by
+    /// default. Change it with [`Cpu::set_clock_hz`] through [`Machine::cpu_mut`].
+    pub fn new() -> Self {
+        Self {
+            scheduler: Scheduler::new(Cpu::new()),
+        }
+    }
+
+    /// Load a ROM and restart the machine. The program space is cleared first, so nothing of a
+    /// previously loaded ROM remains.
+    pub fn load(&mut self, rom: &[u8]) -> Result<(), LoadError> {
+        let cpu = self.scheduler.cpu_mut();
+        cpu.clear_program_space();
+        cpu.load_rom(rom)?;
+        cpu.reset();
+        Ok(())
+    }
+
+    /// Run the machine for `dt` of wall-clock time.
+    pub fn update(&mut self, dt: Duration) -> Result<(), ExecError> {
+        self.scheduler.tick(dt)
+    }
+
+    /// The display contents as a row-major slice of pixels. See [`Machine::display_size`] for
+    /// the dimensions.
+    pub fn framebuffer(&self) -> &[bool] {
+        self.scheduler.cpu().framebuffer()
+    }
+
+    /// Width and height of the display.
+    pub fn display_size(&self) -> (usize, usize) {
+        self.scheduler.cpu().display_size()
+    }
+
+    /// Whether the buzzer should sound.
+    pub fn is_beeping(&self) -> bool {
+        self.scheduler.cpu().is_beeping()
+    }
+
+    /// Press or release a key (0x0-0xF).
+    pub fn set_key(&mut self, key: u8, pressed: bool) {
+        let cpu = self.scheduler.cpu_mut();
+        if pressed {
+            cpu.press_key(key);
+        } else {
+            cpu.release_key(key);
+        }
+    }
+
+    /// The underlying CPU, for anything the machine doesn't expose.
+    pub fn cpu(&self) -> &Cpu {
+        self.scheduler.cpu()
+    }
+
+    pub fn cpu_mut(&mut self) -> &mut Cpu {
+        self.scheduler.cpu_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn update_test() {
+        let mut machine = Machine::new();
+        // Beep for 30 ticks, draw a 0, then halt
+        machine.load(&[0x60, 0x1E, 0xF0, 0x18, 0x61, 0x00, 0xF1, 0x29, 0xD1, 0x15, 0x12, 0x0A]).unwrap();
+        machine.update(Duration::from_millis(100)).unwrap();
+        assert!(machine.is_beeping());
+        assert!(machine.framebuffer()[0]);
+        for _ in 0..60 {
+            machine.update(Duration::from_millis(1000) / 60).unwrap();
+        }
+        assert!(!machine.is_beeping());
+        assert!(machine.cpu().is_halted());
+
+        machine.set_key(0x5, true);
+        assert_eq!(machine.cpu().first_pressed_key(), Some(0x5));
+        machine.set_key(0x5, false);
+        assert_eq!(machine.cpu().first_pressed_key(), None);
+    }
+
+    #[test]
+    fn load_clears_previous_rom_test() {
+        let mut machine = Machine::new();
+        machine.load(&[0x60, 0x01, 0x61, 0x02, 0x12, 0x04]).unwrap();
+        machine.update(Duration::from_millis(100)).unwrap();
+
+        // The shorter ROM falls through into memory the first one used
+        machine.load(&[0x60, 0x05]).unwrap();
+        assert_eq!(machine.cpu().memory_slice(0x202, 4), [0, 0, 0, 0]);
+        machine.cpu_mut().cycle().unwrap();
+        machine.cpu_mut().cycle().unwrap();
+        assert_eq!(machine.cpu().registers()[..2], [5, 0]);
+    }
+
+    #[test]
+    fn clock_hz_test() {
+        let mut machine = Machine::new();
+        machine.load(&[0x12, 0x00]).unwrap();
+        machine.cpu_mut().set_clock_hz(120);
+        machine.update(Duration::from_millis(500)).unwrap();
+        assert_eq!(machine.cpu().cycle_count(), 60);
+    }
+}
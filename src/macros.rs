@@ -0,0 +1,68 @@
+use alloc::vec::Vec;
+
+use crate::instruction::Instruction;
+
+/// Assemble instructions separated by `;` into a `Vec<u8>` ROM, for writing small programs
+/// inline:
+///
+/// ```
+/// let rom = chip8::chip8_rom![LD V0, 5; ADD V0, 1; JP 0x200];
+/// assert_eq!(rom, [0x60, 0x05, 0x70, 0x01, 0x12, 0x00]);
+/// ```
+///
+/// Each instruction uses the syntax of [`Instruction::from_asm`](crate::Instruction::from_asm).
+/// Labels and `db` aren't supported; use [`assemble`](crate::assemble) for those.
+///
+/// # Panics
+///
+/// Panics if an instruction doesn't parse.
+#[macro_export]
+macro_rules! chip8_rom {
+    ($($tokens:tt)*) => {
+        $crate::__rom_from_statements(stringify!($($tokens)*))
+    };
+}
+
+/// Encode the `;`-separated instructions in `source`. Used by [`chip8_rom!`].
+#[doc(hidden)]
+pub fn rom_from_statements(source: &str) -> Vec<u8> {
+    let mut rom = Vec::new();
+    for statement in source.split(';').map(str::trim).filter(|s| !s.is_empty()) {
+        let instr = Instruction::from_asm(statement)
+            .unwrap_or_else(|e| panic!("can't assemble `{statement}`: {e}"));
+        rom.extend_from_slice(&instr.encode().to_be_bytes());
+        if let Instruction::LdILong(addr) = instr {
+            rom.extend_from_slice(&addr.to_be_bytes());
+        }
+    }
+    rom
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cpu::Cpu;
+
+    #[test]
+    fn chip8_rom_test() {
+        let rom = chip8_rom![
+            LD V0, 5;
+            ADD V0, 1;
+            LD I, LONG 0x0300;
+            LD [I], V0;
+            JP 0x20A;
+        ];
+        assert_eq!(rom, [0x60, 0x05, 0x70, 0x01, 0xF0, 0x00, 0x03, 0x00, 0xF0, 0x55, 0x12, 0x0A]);
+
+        let mut cpu = Cpu::new();
+        cpu.load_rom(&rom).unwrap();
+        cpu.run_until_halt(10).unwrap();
+        assert_eq!(cpu.memory_slice(0x300, 1), [6]);
+        assert!(chip8_rom![].is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "can't assemble `LD V0, VX`")]
+    fn chip8_rom_invalid_test() {
+        chip8_rom![LD V0, VX];
+    }
+}
@@ -1,6 +1,3 @@
-mod cpu;
-mod instruction;
-
 fn main() {
     println!("Hello, world!");
 }
@@ -0,0 +1,228 @@
+use alloc::collections::BTreeSet;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+use crate::assembler::parse_number;
+use crate::cpu::Cpu;
+
+/// Cycles `continue` runs before giving up when no breakpoint is hit.
+const CONTINUE_LIMIT: usize = 1_000_000;
+
+/// Bytes shown per line by `mem`.
+const MEM_LINE_LEN: usize = 16;
+
+/// Text command interpreter for a debug console. Each command runs against a [`Cpu`] and
+/// returns its output:
+///
+/// - `step [n]`: execute n instructions (default 1), listing each one
+/// - `regs`: show the registers
+/// - `mem <addr> <len>`: hex dump len bytes of memory starting at addr
+/// - `break [addr]`: set a breakpoint at addr, or list the breakpoints
+/// - `delete <addr>`: remove the breakpoint at addr
+/// - `continue`: run until a breakpoint is reached or the program halts
+///
+/// Numbers are decimal, or hexadecimal or binary with a `0x` or `0b` prefix. The timers
+/// aren't ticked while stepping or continuing.
+#[derive(Clone, Debug, Default)]
+pub struct Monitor {
+    breakpoints: BTreeSet<u16>,
+}
+
+impl Monitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Addresses with a breakpoint, in ascending order.
+    pub fn breakpoints(&self) -> impl Iterator<Item = u16> + '_ {
+        self.breakpoints.iter().copied()
+    }
+
+    /// Run one command and return its output, which is an error message if the command is
+    /// unknown or malformed.
+    pub fn exec_command<const MEM: usize>(&mut self, cpu: &mut Cpu<MEM>, cmd: &str) -> String {
+        let args: Vec<&str> = cmd.split_whitespace().collect();
+        let Some((&name, args)) = args.split_first() else {
+            return String::new();
+        };
+        match (name, args) {
+            ("step", []) => step(cpu, 1),
+            ("step", [n]) => match parse_number(n) {
+                Some(n) => step(cpu, n as usize),
+                None => String::from("usage: step [n]"),
+            },
+            ("step", _) => String::from("usage: step [n]"),
+            ("regs", []) => regs(cpu),
+            ("regs", _) => String::from("usage: regs"),
+            ("mem", [addr, len]) => match (parse_address(addr), parse_number(len)) {
+                (Some(addr), Some(len)) => mem(cpu, addr, len as usize),
+                _ => String::from("usage: mem <addr> <len>"),
+            },
+            ("mem", _) => String::from("usage: mem <addr> <len>"),
+            ("break", []) => {
+                let addrs: Vec<String> = self.breakpoints().map(|addr| format!("{addr:#06x}")).collect();
+                if addrs.is_empty() {
+                    String::from("no breakpoints")
+                } else {
+                    addrs.join("\n")
+                }
+            },
+            ("break", [addr]) => match parse_address(addr) {
+                Some(addr) => {
+                    self.breakpoints.insert(addr);
+                    format!("breakpoint at {addr:#06x}")
+                },
+                None => String::from("usage: break [addr]"),
+            },
+            ("break", _) => String::from("usage: break [addr]"),
+            ("delete", [addr]) => match parse_address(addr) {
+                Some(addr) if self.breakpoints.remove(&addr) => format!("deleted breakpoint at {addr:#06x}"),
+                Some(addr) => format!("no breakpoint at {addr:#06x}"),
+                None => String::from("usage: delete <addr>"),
+            },
+            ("delete", _) => String::from("usage: delete <addr>"),
+            ("continue", []) => self.run(cpu),
+            ("continue", _) => String::from("usage: continue"),
+            _ => format!("unknown command: {name}"),
+        }
+    }
+
+    /// Execute instructions until PC reaches a breakpoint or the program halts. At least one
+    /// instruction runs, so continuing from a breakpoint moves past it.
+    fn run<const MEM: usize>(&self, cpu: &mut Cpu<MEM>) -> String {
+        for _ in 0..CONTINUE_LIMIT {
+            if let Err(e) = cpu.cycle() {
+                return format!("error: {e}");
+            }
+            let pc = cpu.pc();
+            if self.breakpoints.contains(&pc) {
+                return format!("breakpoint at {pc:#06x}");
+            }
+            if cpu.is_halted() {
+                return format!("halted at {pc:#06x}");
+            }
+        }
+        format!("stopped after {CONTINUE_LIMIT} cycles at {:#06x}", cpu.pc())
+    }
+}
+
+fn parse_address(text: &str) -> Option<u16> {
+    parse_number(text)?.try_into().ok()
+}
+
+fn step<const MEM: usize>(cpu: &mut Cpu<MEM>, n: usize) -> String {
+    let mut lines = Vec::new();
+    for _ in 0..n {
+        let line = cpu.trace_line(cpu.pc());
+        if let Err(e) = cpu.cycle() {
+            lines.push(format!("error: {e}"));
+            break;
+        }
+        lines.push(line);
+    }
+    lines.join("\n")
+}
+
+fn regs<const MEM: usize>(cpu: &Cpu<MEM>) -> String {
+    let state = cpu.snapshot();
+    let mut out = format!(
+        "PC={:#06x} I={:#06x} DT={:02X} ST={:02X} SP={}",
+        state.pc, state.index, state.delay_timer, state.sound_timer, state.sp,
+    );
+    for (i, value) in state.reg.iter().enumerate() {
+        let sep = if i % 8 == 0 { '\n' } else { ' ' };
+        let _ = write!(out, "{sep}V{i:X}={value:02X}");
+    }
+    out
+}
+
+fn mem<const MEM: usize>(cpu: &Cpu<MEM>, addr: u16, len: usize) -> String {
+    let bytes = cpu.memory_slice(addr, len);
+    let lines: Vec<String> = bytes
+        .chunks(MEM_LINE_LEN)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let mut line = format!("{:#06x}:", addr as usize + i * MEM_LINE_LEN);
+            for byte in chunk {
+                let _ = write!(line, " {byte:02X}");
+            }
+            line
+        })
+        .collect();
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cpu_with(rom: &[u8]) -> Cpu {
+        let mut cpu = Cpu::new();
+        cpu.load_rom(rom).unwrap();
+        cpu
+    }
+
+    #[test]
+    fn regs_test() {
+        let mut cpu = cpu_with(&[0x60, 0x12, 0x6F, 0xAB, 0xA3, 0x45]);
+        let mut monitor = Monitor::new();
+        monitor.exec_command(&mut cpu, "step 3");
+        assert_eq!(
+            monitor.exec_command(&mut cpu, "regs"),
+            "PC=0x0206 I=0x0345 DT=00 ST=00 SP=0\n\
+             V0=12 V1=00 V2=00 V3=00 V4=00 V5=00 V6=00 V7=00\n\
+             V8=00 V9=00 VA=00 VB=00 VC=00 VD=00 VE=00 VF=AB"
+        );
+    }
+
+    #[test]
+    fn mem_test() {
+        let rom: Vec<u8> = (0..20).collect();
+        let mut cpu = cpu_with(&rom);
+        let mut monitor = Monitor::new();
+        assert_eq!(
+            monitor.exec_command(&mut cpu, "mem 0x202 18"),
+            "0x0202: 02 03 04 05 06 07 08 09 0A 0B 0C 0D 0E 0F 10 11\n\
+             0x0212: 12 13"
+        );
+        // Cut short at the end of memory
+        assert_eq!(monitor.exec_command(&mut cpu, "mem 4094 8"), "0x0ffe: 00 00");
+        assert_eq!(monitor.exec_command(&mut cpu, "mem 0x202"), "usage: mem <addr> <len>");
+    }
+
+    #[test]
+    fn step_test() {
+        let mut cpu = cpu_with(&[0x60, 0x05, 0xFF, 0xFF]);
+        let mut monitor = Monitor::new();
+        assert_eq!(monitor.exec_command(&mut cpu, "step"), "0x0200: 6005  LD V0, 0x05");
+        assert_eq!(
+            monitor.exec_command(&mut cpu, "step 2"),
+            "error: invalid opcode 0xffff at PC 0x0202"
+        );
+    }
+
+    #[test]
+    fn break_continue_test() {
+        // Count V0 up forever
+        let mut cpu = cpu_with(&[0x70, 0x01, 0x12, 0x00]);
+        let mut monitor = Monitor::new();
+        assert_eq!(monitor.exec_command(&mut cpu, "break 0x202"), "breakpoint at 0x0202");
+        assert_eq!(monitor.exec_command(&mut cpu, "break"), "0x0202");
+        assert_eq!(monitor.exec_command(&mut cpu, "continue"), "breakpoint at 0x0202");
+        assert_eq!(monitor.exec_command(&mut cpu, "continue"), "breakpoint at 0x0202");
+        assert_eq!(cpu.registers()[0], 2);
+
+        assert_eq!(monitor.exec_command(&mut cpu, "delete 0x202"), "deleted breakpoint at 0x0202");
+        assert_eq!(monitor.exec_command(&mut cpu, "break"), "no breakpoints");
+        assert_eq!(monitor.exec_command(&mut cpu, "frobnicate 1"), "unknown command: frobnicate");
+    }
+
+    #[test]
+    fn continue_halt_test() {
+        let mut cpu = cpu_with(&[0x60, 0x01, 0x12, 0x02]);
+        let mut monitor = Monitor::new();
+        assert_eq!(monitor.exec_command(&mut cpu, "continue"), "halted at 0x0202");
+    }
+}
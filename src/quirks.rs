@@ -0,0 +1,102 @@
+/// Behaviors that differ between Chip-8 interpreters. The default leaves every option off.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Quirks {
+    /// `8xy6` and `8xyE` shift Vy and store the result in Vx, rather than shifting Vx in place
+    /// (COSMAC VIP behavior).
+    pub shift_uses_vy: bool,
+
+    /// `Fx55` and `Fx65` leave I pointing just past the last register transferred (COSMAC VIP
+    /// behavior).
+    pub memory_increments_index: bool,
+
+    /// `Bnnn` jumps to nnn + Vx, where x is the high nibble of nnn, rather than nnn + V0
+    /// (SUPER-CHIP behavior).
+    pub jump_uses_vx: bool,
+
+    /// `Fx1E` sets VF to 1 when I + Vx overflows past 0x0FFF, and to 0 otherwise (Amiga
+    /// interpreter behavior).
+    pub add_index_sets_vf: bool,
+
+    /// `Dxyn` waits for the vertical blank, so at most one sprite is drawn per 60 Hz frame
+    /// (COSMAC VIP behavior). See [`Cpu::draw_occurred`](crate::Cpu::draw_occurred).
+    pub display_wait: bool,
+
+    /// Sprite pixels drawn past the edge of the screen wrap around to the opposite side
+    /// instead of being clipped.
+    pub sprite_wrapping: bool,
+
+    /// In high resolution mode, `Dxyn` sets VF to the number of sprite rows that collided plus
+    /// the number of rows clipped at the bottom edge, rather than to 1 on any collision
+    /// (SUPER-CHIP 1.1 behavior).
+    pub drw_count_clipped_rows: bool,
+}
+
+impl Quirks {
+    /// Behavior of the original COSMAC VIP interpreter.
+    pub fn cosmac() -> Self {
+        Self {
+            shift_uses_vy: true,
+            memory_increments_index: true,
+            jump_uses_vx: false,
+            add_index_sets_vf: false,
+            display_wait: true,
+            sprite_wrapping: false,
+            drw_count_clipped_rows: false,
+        }
+    }
+
+    /// Behavior of SUPER-CHIP 1.1, which most modern Chip-8 programs expect.
+    pub fn schip() -> Self {
+        Self {
+            shift_uses_vy: false,
+            memory_increments_index: false,
+            jump_uses_vx: true,
+            add_index_sets_vf: false,
+            display_wait: false,
+            sprite_wrapping: false,
+            drw_count_clipped_rows: true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cosmac_test() {
+        let quirks = Quirks::cosmac();
+        assert!(quirks.shift_uses_vy);
+        assert!(quirks.memory_increments_index);
+        assert!(!quirks.jump_uses_vx);
+        assert!(!quirks.add_index_sets_vf);
+        assert!(quirks.display_wait);
+        assert!(!quirks.sprite_wrapping);
+        assert!(!quirks.drw_count_clipped_rows);
+    }
+
+    #[test]
+    fn schip_test() {
+        let quirks = Quirks::schip();
+        assert!(!quirks.shift_uses_vy);
+        assert!(!quirks.memory_increments_index);
+        assert!(quirks.jump_uses_vx);
+        assert!(!quirks.add_index_sets_vf);
+        assert!(!quirks.display_wait);
+        assert!(!quirks.sprite_wrapping);
+        assert!(quirks.drw_count_clipped_rows);
+    }
+
+    #[test]
+    fn default_test() {
+        let quirks = Quirks::default();
+        assert!(!quirks.shift_uses_vy);
+        assert!(!quirks.memory_increments_index);
+        assert!(!quirks.jump_uses_vx);
+        assert!(!quirks.add_index_sets_vf);
+        assert!(!quirks.display_wait);
+        assert!(!quirks.sprite_wrapping);
+        assert!(!quirks.drw_count_clipped_rows);
+    }
+}
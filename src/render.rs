@@ -0,0 +1,50 @@
+use alloc::vec::Vec;
+
+/// Convert a row-major framebuffer into RGBA pixel data, scaling each pixel up to a
+/// `scale` x `scale` block of the `on` or `off` color. The output image is
+/// `width * scale` x `height * scale` pixels, so a zero width gives an empty image.
+pub fn render_rgba(fb: &[bool], width: usize, height: usize, scale: usize, on: [u8; 4], off: [u8; 4]) -> Vec<u8> {
+    if width == 0 {
+        return Vec::new();
+    }
+    let mut out = Vec::with_capacity(width * height * scale * scale * 4);
+    for row in fb.chunks(width).take(height) {
+        for _ in 0..scale {
+            for &pixel in row {
+                let color = if pixel { on } else { off };
+                for _ in 0..scale {
+                    out.extend_from_slice(&color);
+                }
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_rgba_test() {
+        const ON: [u8; 4] = [0xff, 0xff, 0xff, 0xff];
+        const OFF: [u8; 4] = [0x00, 0x00, 0x00, 0xff];
+        let fb = [true, false, false, true];
+        let rgba = render_rgba(&fb, 2, 2, 2, ON, OFF);
+
+        let expected_rows = [
+            [ON, ON, OFF, OFF],
+            [ON, ON, OFF, OFF],
+            [OFF, OFF, ON, ON],
+            [OFF, OFF, ON, ON],
+        ];
+        assert_eq!(rgba, expected_rows.concat().concat());
+        assert!(render_rgba(&fb, 0, 2, 2, ON, OFF).is_empty());
+    }
+
+    #[test]
+    fn render_rgba_unscaled_test() {
+        let rgba = render_rgba(&[false, true], 2, 1, 1, [1, 2, 3, 4], [5, 6, 7, 8]);
+        assert_eq!(rgba, [5, 6, 7, 8, 1, 2, 3, 4]);
+    }
+}
@@ -0,0 +1,91 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+/// Source of random bytes for the `RND` instruction. Implement it to replace the default
+/// [`XorShift`] generator, e.g. with [`FixedRng`] in tests.
+pub trait Rng: RngClone {
+    fn next_byte(&mut self) -> u8;
+}
+
+/// Lets a boxed [`Rng`] be cloned along with the CPU that owns it. Implemented automatically
+/// for every `Rng` that is `Clone`.
+pub trait RngClone {
+    fn clone_box(&self) -> Box<dyn Rng>;
+}
+
+impl<T: Rng + Clone + 'static> RngClone for T {
+    fn clone_box(&self) -> Box<dyn Rng> {
+        Box::new(self.clone())
+    }
+}
+
+/// Seed used when none is provided. Xorshift gets stuck at zero, so zero can't be used.
+const DEFAULT_SEED: u64 = 0x2545_f491_4f6c_dd1d;
+
+/// Xorshift pseudo-random number generator used by the `RND` instruction.
+#[derive(Clone, Debug)]
+pub struct XorShift {
+    state: u64,
+}
+
+impl XorShift {
+    /// Create a new generator. A seed of zero is replaced with the default seed.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            state: if seed == 0 { DEFAULT_SEED } else { seed },
+        }
+    }
+}
+
+impl Rng for XorShift {
+    /// Advance the generator and return a random byte.
+    fn next_byte(&mut self) -> u8 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        (self.state >> 32) as u8
+    }
+}
+
+impl Default for XorShift {
+    fn default() -> Self {
+        Self::new(DEFAULT_SEED)
+    }
+}
+
+/// Generator that returns a scripted sequence of bytes, starting over once it runs out. An
+/// empty sequence always returns zero.
+#[derive(Clone, Debug)]
+pub struct FixedRng {
+    bytes: Vec<u8>,
+    next: usize,
+}
+
+impl FixedRng {
+    pub fn new(bytes: Vec<u8>) -> Self {
+        Self { bytes, next: 0 }
+    }
+}
+
+impl Rng for FixedRng {
+    fn next_byte(&mut self) -> u8 {
+        let Some(&byte) = self.bytes.get(self.next) else {
+            return 0;
+        };
+        self.next = (self.next + 1) % self.bytes.len();
+        byte
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixed_rng_test() {
+        let mut rng = FixedRng::new(vec![1, 2, 3]);
+        let bytes: Vec<u8> = (0..5).map(|_| rng.next_byte()).collect();
+        assert_eq!(bytes, [1, 2, 3, 1, 2]);
+        assert_eq!(FixedRng::new(Vec::new()).next_byte(), 0);
+    }
+}
@@ -0,0 +1,147 @@
+use core::time::Duration;
+
+use crate::cpu::{Cpu, NANOS_PER_SEC, TIMER_HZ};
+use crate::error::ExecError;
+
+/// Drives a [`Cpu`] in simulated time, running cycles at the CPU's clock rate
+/// ([`Cpu::clock_hz`]) and ticking the timers at 60 Hz.
+///
+/// Cycles and ticks are dispatched in the order they fall due: cycle k runs at k / clock_hz
+/// seconds and tick j at j / 60 seconds, compared exactly without rounding. However `tick` is
+/// called, the same amount of total time runs the same sequence of cycles and ticks. With cycle
+/// costs enabled, cycles are machine cycles and each instruction uses up its cost, as with
+/// [`Cpu::advance`].
+pub struct Scheduler<const MEM: usize = 4096> {
+    cpu: Cpu<MEM>,
+    /// Clock rate of the CPU when the counts below were started
+    clock_hz: u32,
+    /// Simulated time since the clock rate last changed, in nanoseconds
+    elapsed: u128,
+    /// Cycles and ticks run since the clock rate last changed
+    cycles_run: u128,
+    ticks_run: u128,
+    /// Total timer ticks run
+    timer_ticks: u64,
+}
+
+impl<const MEM: usize> Scheduler<MEM> {
+    /// Schedule `cpu` to run at its clock rate.
+    pub fn new(cpu: Cpu<MEM>) -> Self {
+        Self {
+            clock_hz: cpu.clock_hz(),
+            cpu,
+            elapsed: 0,
+            cycles_run: 0,
+            ticks_run: 0,
+            timer_ticks: 0,
+        }
+    }
+
+    pub fn cpu(&self) -> &Cpu<MEM> {
+        &self.cpu
+    }
+
+    pub fn cpu_mut(&mut self) -> &mut Cpu<MEM> {
+        &mut self.cpu
+    }
+
+    /// Stop scheduling and return the CPU.
+    pub fn into_cpu(self) -> Cpu<MEM> {
+        self.cpu
+    }
+
+    /// Number of timer ticks run so far.
+    pub fn timer_ticks(&self) -> u64 {
+        self.timer_ticks
+    }
+
+    /// Advance simulated time by `dt`, running every cycle and timer tick that falls due. If the
+    /// CPU's clock rate has changed, time since the last cycle or tick is discarded.
+    pub fn tick(&mut self, dt: Duration) -> Result<(), ExecError> {
+        if self.cpu.clock_hz() != self.clock_hz {
+            self.clock_hz = self.cpu.clock_hz();
+            self.elapsed = 0;
+            self.cycles_run = 0;
+            self.ticks_run = 0;
+        }
+
+        self.elapsed += dt.as_nanos();
+        let cpu_hz = self.clock_hz as u128;
+        let cycles_due = self.elapsed * cpu_hz / NANOS_PER_SEC;
+        let ticks_due = self.elapsed * TIMER_HZ / NANOS_PER_SEC;
+
+        while self.cycles_run < cycles_due || self.ticks_run < ticks_due {
+            // Cycle k + 1 is due before tick j + 1 if (k + 1) / cpu_hz <= (j + 1) / 60
+            let cycle_first = self.ticks_run == ticks_due
+                || (self.cycles_run < cycles_due
+                    && (self.cycles_run + 1) * TIMER_HZ <= (self.ticks_run + 1) * cpu_hz);
+            if cycle_first {
+                let before = self.cpu.elapsed_machine_cycles();
+                self.cpu.cycle()?;
+                self.cycles_run += if self.cpu.cycle_costs_enabled() {
+                    // A cycle spent waiting for the display still takes time
+                    (self.cpu.elapsed_machine_cycles() - before).max(1) as u128
+                } else {
+                    1
+                };
+            } else {
+                self.cpu.tick_timers();
+                self.ticks_run += 1;
+                self.timer_ticks += 1;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn one_second_test() {
+        let mut cpu = Cpu::new();
+        cpu.load_rom(&[0x12, 0x00]).unwrap();
+        let mut scheduler = Scheduler::new(cpu);
+        for _ in 0..1000 {
+            scheduler.tick(Duration::from_millis(1)).unwrap();
+        }
+        assert_eq!(scheduler.timer_ticks(), 60);
+        assert_eq!(scheduler.cpu().cycle_count(), 700);
+    }
+
+    #[test]
+    fn interleave_test() {
+        let mut cpu = Cpu::new();
+        cpu.load_rom(&[0x12, 0x00]).unwrap();
+        cpu.set_clock_hz(600);
+        let mut scheduler = Scheduler::new(cpu);
+        // Just short of 1/60 s, 9 cycles are due and no ticks
+        scheduler.tick(Duration::from_nanos(16_666_666)).unwrap();
+        assert_eq!(scheduler.cpu().cycle_count(), 9);
+        assert_eq!(scheduler.timer_ticks(), 0);
+        // The 10th cycle and the first tick are both due at 1/60 s
+        scheduler.tick(Duration::from_nanos(1)).unwrap();
+        assert_eq!(scheduler.cpu().cycle_count(), 10);
+        assert_eq!(scheduler.timer_ticks(), 1);
+
+        // Changing the CPU's clock rate takes effect on the next tick
+        scheduler.cpu_mut().set_clock_hz(60);
+        scheduler.tick(Duration::from_millis(100)).unwrap();
+        assert_eq!(scheduler.cpu().cycle_count(), 16);
+        assert_eq!(scheduler.timer_ticks(), 7);
+    }
+
+    #[test]
+    fn cycle_costs_test() {
+        let mut cpu = Cpu::new();
+        // Draw in a loop: each iteration costs 8 + 1 machine cycles
+        cpu.load_rom(&[0xD0, 0x15, 0x12, 0x00]).unwrap();
+        cpu.set_clock_hz(900);
+        cpu.set_cycle_costs_enabled(true);
+        let mut scheduler = Scheduler::new(cpu);
+        scheduler.tick(Duration::from_millis(100)).unwrap();
+        assert_eq!(scheduler.cpu().elapsed_machine_cycles(), 90);
+        assert_eq!(scheduler.cpu().cycle_count(), 20);
+    }
+}
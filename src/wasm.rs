@@ -0,0 +1,80 @@
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
+use wasm_bindgen::prelude::*;
+
+use crate::cpu::Cpu;
+
+/// JavaScript bindings for running a [`Cpu`] in a browser.
+#[wasm_bindgen]
+pub struct WasmCpu {
+    cpu: Cpu,
+}
+
+#[wasm_bindgen]
+impl WasmCpu {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self { cpu: Cpu::new() }
+    }
+
+    pub fn load_rom(&mut self, rom: &[u8]) -> Result<(), JsError> {
+        self.cpu.load_rom(rom).map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    pub fn cycle(&mut self) -> Result<(), JsError> {
+        self.cpu.cycle().map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    pub fn tick_timers(&mut self) {
+        self.cpu.tick_timers();
+    }
+
+    pub fn press_key(&mut self, key: u8) {
+        self.cpu.press_key(key);
+    }
+
+    pub fn release_key(&mut self, key: u8) {
+        self.cpu.release_key(key);
+    }
+
+    /// The display contents with one byte per pixel (1 = set, 0 = unset), row by row. Use
+    /// `width` and `height` to find the active resolution.
+    pub fn framebuffer(&self) -> Vec<u8> {
+        self.cpu.framebuffer().iter().map(|&pixel| pixel as u8).collect()
+    }
+
+    pub fn width(&self) -> usize {
+        self.cpu.display_size().0
+    }
+
+    pub fn height(&self) -> usize {
+        self.cpu.display_size().1
+    }
+}
+
+impl Default for WasmCpu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn framebuffer_test() {
+        let mut cpu = WasmCpu::new();
+        // Draw the font sprite for 0 at (0, 0)
+        cpu.load_rom(&[0x60, 0x00, 0xF0, 0x29, 0xD0, 0x05]).unwrap();
+        for _ in 0..3 {
+            cpu.cycle().unwrap();
+        }
+
+        let framebuffer = cpu.framebuffer();
+        assert_eq!(framebuffer.len(), cpu.width() * cpu.height());
+        assert_eq!(framebuffer[..5], [1, 1, 1, 1, 0]);
+        assert_eq!(framebuffer[64..69], [1, 0, 0, 1, 0]);
+    }
+}
@@ -0,0 +1,87 @@
+//! Checks that the assembler and disassembler agree: assembling a listing produced by the
+//! disassembler gives back the same bytes.
+
+use chip8::{assemble, disassemble};
+
+/// One or more of every kind of instruction.
+const SOURCE: &str = "
+    start:
+        CLS
+        CALL sub
+        SYS 0x123
+        JP start
+        JP V0, 0x300
+
+    ; Conditionals
+        SE V1, 0x10
+        SNE V2, 255
+        SE V3, V4
+        SNE V5, V6
+        SKP V7
+        SKNP V8
+
+    ; Arithmetic and logic
+        LD V9, 0b1010
+        ADD VA, 1
+        LD VB, VC
+        OR VD, VE
+        AND V0, V1
+        XOR V2, V3
+        ADD V4, V5
+        SUB V6, V7
+        SHR V8, V9
+        SUBN VA, VB
+        SHL VC
+        RND VD, 0x0F
+
+    ; Memory, timers and input
+        LD I, sprite
+        LD I, LONG sprite
+        ADD I, VE
+        LD F, V0
+        LD HF, V1
+        LD B, V2
+        LD [I], V3
+        LD V4, [I]
+        LD V5, DT
+        LD DT, V6
+        LD ST, V7
+        LD V8, K
+        LD R, V7
+        LD V6, R
+        AUDIO
+        PITCH V5
+
+    ; Display
+        DRW V0, V1, 5
+        SCD 4
+        SCR
+        SCL
+        LOW
+        HIGH
+        PLANE 3
+
+    sub:
+        RET
+    sprite:
+";
+
+#[test]
+fn roundtrip_test() {
+    let rom = assemble(SOURCE).unwrap();
+
+    let listing: Vec<String> = disassemble(&rom, 0x200)
+        .map(|(addr, opcode, instr)| {
+            let instr = instr.unwrap_or_else(|| panic!("{opcode:04X} at {addr:#06x} doesn't decode"));
+            instr.to_string()
+        })
+        .collect();
+    let reassembled = assemble(&listing.join("\n")).unwrap();
+    assert_eq!(reassembled, rom);
+
+    // Assembling is deterministic, so going around again changes nothing
+    let listing: Vec<String> = disassemble(&reassembled, 0x200)
+        .map(|(_, _, instr)| instr.unwrap().to_string())
+        .collect();
+    assert_eq!(assemble(&listing.join("\n")).unwrap(), rom);
+}
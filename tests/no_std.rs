@@ -0,0 +1,21 @@
+//! Compile check for using the crate without the standard library. Run with
+//! `cargo test --no-default-features`.
+#![cfg(not(feature = "std"))]
+#![no_std]
+
+use chip8::{Cpu, CpuBuilder, Quirks};
+
+#[test]
+fn no_std_test() {
+    let mut cpu: Cpu = CpuBuilder::new()
+        .seed(1)
+        .quirks(Quirks::default())
+        .rom(&[0x60, 0x05, 0xC1, 0xFF, 0x12, 0x04])
+        .build()
+        .unwrap();
+    for _ in 0..3 {
+        cpu.cycle().unwrap();
+    }
+    cpu.tick_timers();
+    assert_eq!(cpu.snapshot().reg[0], 5);
+}
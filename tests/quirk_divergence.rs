@@ -0,0 +1,39 @@
+//! Runs the same ROM under the COSMAC VIP and SUPER-CHIP quirks side by side, checking that they
+//! agree until the first instruction whose behavior differs between them.
+
+use chip8::{chip8_rom, Cpu, CpuBuilder, Quirks};
+
+fn build(quirks: Quirks, rom: &[u8]) -> Cpu {
+    CpuBuilder::new().quirks(quirks).rom(rom).build().unwrap()
+}
+
+#[test]
+fn shift_divergence_test() {
+    let rom = chip8_rom![
+        LD V1, 0x10;
+        LD V2, 0x07;
+        ADD V1, V2;
+        SHR V1, V2;
+        JP 0x208;
+    ];
+    let mut cosmac = build(Quirks::cosmac(), &rom);
+    let mut schip = build(Quirks::schip(), &rom);
+
+    // Step both until their registers first differ
+    let mut steps = 0;
+    while cosmac.registers() == schip.registers() {
+        assert!(steps < 10, "the quirk configurations never diverged");
+        cosmac.cycle().unwrap();
+        schip.cycle().unwrap();
+        steps += 1;
+    }
+
+    // They diverge at SHR: the COSMAC VIP shifts VY into VX, SUPER-CHIP shifts VX in place
+    assert_eq!(steps, 4);
+    assert_eq!(cosmac.snapshot().pc, 0x208);
+    assert_eq!(cosmac.registers()[1], 0x07 >> 1);
+    assert_eq!(schip.registers()[1], 0x17 >> 1);
+    // Both shifted out a 1
+    assert_eq!(cosmac.registers()[0xf], 1);
+    assert_eq!(schip.registers()[0xf], 1);
+}
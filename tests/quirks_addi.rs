@@ -0,0 +1,57 @@
+//! `ADD I, Vx` (Fx1E) at the 0x0FFF boundary, with and without the Amiga `add_index_sets_vf`
+//! quirk.
+
+use chip8::{Cpu, CpuBuilder, Quirks};
+
+/// Run `LD I, 0xFFF` then `ADD I, V1` with V1 = `v1` and VF = 0xAA, returning the CPU.
+fn add_i(quirks: Quirks, v1: u8) -> Cpu {
+    let rom = [0x61, v1, 0x6F, 0xAA, 0xAF, 0xFF, 0xF1, 0x1E];
+    let mut cpu = CpuBuilder::new().quirks(quirks).rom(&rom).build().unwrap();
+    for _ in 0..4 {
+        cpu.cycle().unwrap();
+    }
+    cpu
+}
+
+fn amiga() -> Quirks {
+    Quirks { add_index_sets_vf: true, ..Quirks::default() }
+}
+
+#[test]
+fn amiga_overflow_test() {
+    let state = add_i(amiga(), 1).snapshot();
+    assert_eq!(state.index, 0x1000);
+    assert_eq!(state.reg[0xf], 1);
+}
+
+#[test]
+fn amiga_no_overflow_test() {
+    let state = add_i(amiga(), 0).snapshot();
+    assert_eq!(state.index, 0x0fff);
+    assert_eq!(state.reg[0xf], 0);
+}
+
+#[test]
+fn default_leaves_vf_test() {
+    for v1 in [0, 1, 0xff] {
+        let state = add_i(Quirks::default(), v1).snapshot();
+        assert_eq!(state.index, 0x0fff + v1 as u16);
+        assert_eq!(state.reg[0xf], 0xaa);
+    }
+}
+
+#[test]
+fn wrapping_test() {
+    // I is 16 bits wide, so the sum only wraps past 0xFFFF
+    for quirks in [Quirks::default(), amiga()] {
+        let mut cpu = CpuBuilder::new()
+            .quirks(quirks)
+            .rom(&[0xF0, 0x00, 0xFF, 0xFF, 0x61, 0x02, 0xF1, 0x1E])
+            .build()
+            .unwrap();
+        for _ in 0..3 {
+            cpu.cycle().unwrap();
+        }
+        assert_eq!(cpu.snapshot().index, 0x0001);
+    }
+}
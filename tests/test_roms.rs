@@ -0,0 +1,106 @@
+//! Runs small test ROMs headlessly and compares the final screen against a known-good bitmap.
+//! ROMs live in `tests/roms/`; others (such as the Timendus chip8-test-suite ROMs) can be
+//! added the same way.
+
+use chip8::{Cpu, CpuBuilder};
+
+/// Load a ROM and run it for a fixed number of cycles.
+fn run_rom(rom: &[u8], cycles: usize) -> Cpu {
+    let mut cpu = CpuBuilder::new().rom(rom).build().unwrap();
+    for _ in 0..cycles {
+        cpu.cycle().unwrap();
+    }
+    cpu
+}
+
+/// Strip the indentation from an expected bitmap written inline in a test.
+fn bitmap(lines: &str) -> String {
+    lines.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| format!("{line}\n"))
+        .collect()
+}
+
+#[test]
+fn ibm_logo_test() {
+    let cpu = run_rom(include_bytes!("roms/ibm-logo.ch8"), 100);
+    let expected = bitmap("
+        ................................................................
+        ................................................................
+        ................................................................
+        ................................................................
+        ................................................................
+        ................................................................
+        ................................................................
+        ................................................................
+        ............########.#########...#####.........#####............
+        ................................................................
+        ............########.###########.######.......######............
+        ................................................................
+        ..............####.....###...###...#####.....#####..............
+        ................................................................
+        ..............####.....#######.....#######.#######..............
+        ................................................................
+        ..............####.....#######.....###.#######.###..............
+        ................................................................
+        ..............####.....###...###...###..#####..###..............
+        ................................................................
+        ............########.###########.#####...###...#####............
+        ................................................................
+        ............########.#########...#####....#....#####............
+        ................................................................
+        ................................................................
+        ................................................................
+        ................................................................
+        ................................................................
+        ................................................................
+        ................................................................
+        ................................................................
+        ................................................................
+    ");
+    assert_eq!(cpu.render_ascii(), expected);
+}
+
+/// The flags ROM draws the VF result of nine arithmetic and shift instructions on the first
+/// row, the results of six more on the second row, and the VF of a non-colliding and a
+/// colliding DRW on the third.
+#[test]
+fn flags_test() {
+    let cpu = run_rom(include_bytes!("roms/flags.ch8"), 200);
+    let expected = bitmap("
+        ..#..####...#..####...#..####...#....#..####....................
+        .##..#..#..##..#..#..##..#..#..##...##..#..#....................
+        ..#..#..#...#..#..#...#..#..#...#....#..#..#....................
+        ..#..#..#...#..#..#...#..#..#...#....#..#..#....................
+        .###.####..###.####..###.####..###..###.####....................
+        ................................................................
+        ................................................................
+        ................................................................
+        ####.####.####.####.####.####...................................
+        #..#....#.#..#.#....#..#....#...................................
+        ####...#..####.####.####.####...................................
+        #..#..#......#.#..#.#..#....#...................................
+        ####..#...####.####.#..#.####...................................
+        ................................................................
+        ................................................................
+        ................................................................
+        ####...#........................................................
+        #..#..##........................................................
+        #..#...#........................................................
+        #..#...#........................................................
+        ####..###.......................................................
+        ................................................................
+        ................................................................
+        ................................................................
+        ................................................................
+        ................................................................
+        ................................................................
+        ................................................................
+        ................................................................
+        ................................................................
+        ................................................................
+        ................................................................
+    ");
+    assert_eq!(cpu.render_ascii(), expected);
+}